@@ -1,54 +1,275 @@
+use crate::door::Doors;
 use crate::framebuffer::Framebuffer;
+use crate::maze::Maze;
 use crate::player::Player;
+use nalgebra_glm::Vec2;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Face {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Face {
+    pub fn normal(&self) -> Vec2 {
+        match self {
+            Face::North => Vec2::new(0.0, -1.0),
+            Face::South => Vec2::new(0.0, 1.0),
+            Face::East => Vec2::new(1.0, 0.0),
+            Face::West => Vec2::new(-1.0, 0.0),
+        }
+    }
+}
+
+/// Where a ray stopped. A ray that runs off the map or out of range without
+/// striking anything ends in a miss, with `' '` as its impact.
 pub struct Intersect {
     pub distance: f32,
     pub impact: char,
-    pub tx: usize,
+    pub hit: Vec2,
+    pub cell: (usize, usize),
+    pub face: Face,
+    pub u: f32,
 }
 
-pub fn cast_ray(
-    framebuffer: &mut Framebuffer,
-    maze: &Vec<Vec<char>>,
-    player: &Player,
-    angle: f32,
-    block_size: usize,
-    draw_line: bool,
-) -> Intersect {
-    let mut d = 0.0;
+/// Walks a ray through the grid one cell boundary at a time, yielding every
+/// wall or door it strikes in order of distance until it leaves the maze or
+/// passes its maximum distance.
+pub struct Ray<'a> {
+    maze: &'a Maze,
+    doors: &'a Doors,
+    pos: Vec2,
+    dir: Vec2,
+    block: f32,
+    origin: Vec2,
+    i: isize,
+    j: isize,
+    step_x: isize,
+    step_y: isize,
+    side_x: f32,
+    side_y: f32,
+    delta_x: f32,
+    delta_y: f32,
+    travelled: f32,
+    max_distance: f32,
+}
 
-    framebuffer.set_current_color(0xFFFFFF);
+impl Intersect {
+    pub fn is_hit(&self) -> bool {
+        self.impact != ' '
+    }
+}
 
-    loop {
-        let cos = d * angle.cos();
-        let sin = d * angle.sin();
+impl<'a> Ray<'a> {
+    pub fn new(maze: &'a Maze, doors: &'a Doors, pos: Vec2, angle: f32, block_size: usize) -> Self {
+        let block = block_size as f32;
+        let dir = Vec2::new(angle.cos(), angle.sin());
 
-        let x = (player.pos.x + cos) as usize;
-        let y = (player.pos.y + sin) as usize;
+        // Work in cell units: the ray crosses one grid line per step, so the
+        // cost depends on how many cells it passes instead of how far it goes.
+        let origin = pos / block;
+        let i = origin.x.floor() as isize;
+        let j = origin.y.floor() as isize;
 
-        let i = x / block_size;
-        let j = y / block_size;
+        let delta_x = if dir.x == 0.0 {
+            f32::INFINITY
+        } else {
+            (1.0 / dir.x).abs()
+        };
+        let delta_y = if dir.y == 0.0 {
+            f32::INFINITY
+        } else {
+            (1.0 / dir.y).abs()
+        };
 
-        let hitx: usize = x - i * block_size;
-        let hity: usize = y - j * block_size;
-        let mut maxhit = hity;
+        let (step_x, side_x) = if dir.x < 0.0 {
+            (-1, (origin.x - i as f32) * delta_x)
+        } else {
+            (1, (i as f32 + 1.0 - origin.x) * delta_x)
+        };
+        let (step_y, side_y) = if dir.y < 0.0 {
+            (-1, (origin.y - j as f32) * delta_y)
+        } else {
+            (1, (j as f32 + 1.0 - origin.y) * delta_y)
+        };
 
-        if 1 < hitx && hitx < block_size - 1 {
-            maxhit = hitx;
+        Ray {
+            maze,
+            doors,
+            pos,
+            dir,
+            block,
+            origin,
+            i,
+            j,
+            step_x,
+            step_y,
+            side_x,
+            side_y,
+            delta_x,
+            delta_y,
+            travelled: 0.0,
+            max_distance: f32::INFINITY,
         }
+    }
 
-        if draw_line {
-            framebuffer.point(x, y);
+    /// Stops the ray `distance` world units from its start.
+    pub fn with_max_distance(mut self, distance: f32) -> Self {
+        self.max_distance = distance;
+        self
+    }
+
+    /// The intersect of a ray that ran out of map or range, placed at the
+    /// farthest point it reached.
+    pub fn miss(&self) -> Intersect {
+        let distance = (self.travelled * self.block).min(self.max_distance);
+        let last_x = self.maze.width() as isize - 1;
+        let last_y = self.maze.height() as isize - 1;
+        let face = if self.dir.x.abs() > self.dir.y.abs() {
+            if self.dir.x > 0.0 {
+                Face::West
+            } else {
+                Face::East
+            }
+        } else if self.dir.y > 0.0 {
+            Face::North
+        } else {
+            Face::South
+        };
+        Intersect {
+            distance,
+            impact: ' ',
+            hit: self.pos + self.dir * distance,
+            cell: (
+                self.i.clamp(0, last_x) as usize,
+                self.j.clamp(0, last_y) as usize,
+            ),
+            face,
+            u: 0.0,
         }
+    }
+
+    fn intersect(&self, t: f32, face: Face, door_u: Option<f32>) -> Intersect {
+        let (i, j) = (self.i, self.j);
+        let distance = t * self.block;
+        let hit = self.pos + self.dir * distance;
+
+        // Flip the coordinate on two faces so textures read left to right when
+        // seen from outside the cell, whichever side the ray came from.
+        let frac_x = (hit.x / self.block - i as f32).clamp(0.0, 1.0);
+        let frac_y = (hit.y / self.block - j as f32).clamp(0.0, 1.0);
+        let u = door_u.unwrap_or(match face {
+            Face::West => frac_y,
+            Face::East => 1.0 - frac_y,
+            Face::North => 1.0 - frac_x,
+            Face::South => frac_x,
+        });
 
-        if maze[j][i] != ' ' {
-            return Intersect {
-                distance: d,
-                impact: maze[j][i],
-                tx: maxhit * (128 / block_size),
+        Intersect {
+            distance,
+            impact: self.maze.rows()[j as usize][i as usize],
+            hit,
+            cell: (i as usize, j as usize),
+            face,
+            u,
+        }
+    }
+}
+
+impl Iterator for Ray<'_> {
+    type Item = Intersect;
+
+    fn next(&mut self) -> Option<Intersect> {
+        loop {
+            let face = if self.side_x < self.side_y {
+                self.travelled = self.side_x;
+                self.side_x += self.delta_x;
+                self.i += self.step_x;
+                if self.step_x > 0 {
+                    Face::West
+                } else {
+                    Face::East
+                }
+            } else {
+                self.travelled = self.side_y;
+                self.side_y += self.delta_y;
+                self.j += self.step_y;
+                if self.step_y > 0 {
+                    Face::North
+                } else {
+                    Face::South
+                }
             };
+
+            let (i, j) = (self.i, self.j);
+            if i < 0 || j < 0 || self.travelled * self.block > self.max_distance {
+                return None;
+            }
+            let cell = self.maze.cell(i as usize, j as usize)?;
+            if cell == ' ' {
+                continue;
+            }
+
+            // Doors are thin panels across the middle of their cell that slide
+            // sideways as they open; rays through the open part keep going.
+            if let Some(door) = self.doors.get(i as usize, j as usize) {
+                let (origin, dir) = (self.origin, self.dir);
+                let (t, along, face) = if door.horizontal {
+                    let t = (j as f32 + 0.5 - origin.y) / dir.y;
+                    let face = if dir.y > 0.0 {
+                        Face::North
+                    } else {
+                        Face::South
+                    };
+                    (t, origin.x + dir.x * t - i as f32, face)
+                } else {
+                    let t = (i as f32 + 0.5 - origin.x) / dir.x;
+                    let face = if dir.x > 0.0 { Face::West } else { Face::East };
+                    (t, origin.y + dir.y * t - j as f32, face)
+                };
+                if t >= 0.0
+                    && t * self.block <= self.max_distance
+                    && (0.0..1.0).contains(&along)
+                    && along >= door.open
+                {
+                    return Some(self.intersect(t, face, Some(along - door.open)));
+                }
+                continue;
+            }
+
+            let t = match face {
+                Face::West | Face::East => self.side_x - self.delta_x,
+                Face::North | Face::South => self.side_y - self.delta_y,
+            };
+            return Some(self.intersect(t, face, None));
         }
+    }
+}
 
-        d += 1.0;
+pub fn cast_ray(
+    framebuffer: &mut Framebuffer,
+    maze: &Maze,
+    doors: &Doors,
+    player: &Player,
+    angle: f32,
+    block_size: usize,
+    draw_line: bool,
+) -> Intersect {
+    let mut ray = Ray::new(maze, doors, player.pos, angle, block_size);
+    let intersect = ray.next().unwrap_or_else(|| ray.miss());
+
+    if draw_line {
+        framebuffer.set_current_color(0xFFFFFF);
+        let dir = Vec2::new(angle.cos(), angle.sin());
+        let mut d = 0.0;
+        while d < intersect.distance {
+            let point = player.pos + dir * d;
+            framebuffer.point(point.x as usize, point.y as usize);
+            d += 1.0;
+        }
     }
+
+    intersect
 }
@@ -0,0 +1,45 @@
+//! Moving the mouse pointer, which minifb has no call for. Mouse look puts
+//! the pointer back in the middle of the window with this whenever it
+//! strays, so it never reaches the edge of the window or the screen.
+//!
+//! Only X11 is supported. Elsewhere, including Wayland, `warp` reports
+//! failure and the pointer is left free.
+
+use minifb::Window;
+
+/// Moves the pointer to `(x, y)` in window pixels. Returns false where this
+/// isn't supported, which is everywhere but X11 for now.
+#[cfg(target_os = "linux")]
+pub fn warp(window: &Window, x: f32, y: f32) -> bool {
+    use once_cell::sync::Lazy;
+    use raw_window_handle::{HasDisplayHandle, HasWindowHandle, RawDisplayHandle, RawWindowHandle};
+    use x11_dl::xlib::{self, Xlib};
+
+    static XLIB: Lazy<Option<Xlib>> = Lazy::new(|| Xlib::open().ok());
+
+    let (Ok(window_handle), Ok(display_handle)) = (window.window_handle(), window.display_handle())
+    else {
+        return false;
+    };
+    let (RawWindowHandle::Xlib(window), RawDisplayHandle::Xlib(display)) =
+        (window_handle.as_raw(), display_handle.as_raw())
+    else {
+        return false;
+    };
+    let (Some(xlib), Some(display)) = (XLIB.as_ref(), display.display) else {
+        return false;
+    };
+    let display = display.as_ptr() as *mut xlib::Display;
+    unsafe {
+        (xlib.XWarpPointer)(display, 0, window.window, 0, 0, 0, 0, x as i32, y as i32);
+        // Send the request now rather than whenever Xlib's buffer fills.
+        // The motion event comes back before minifb polls the next frame.
+        (xlib.XFlush)(display);
+    }
+    true
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn warp(_window: &Window, _x: f32, _y: f32) -> bool {
+    false
+}
@@ -0,0 +1,116 @@
+use crate::maze::Maze;
+use nalgebra_glm::Vec2;
+
+/// Fraction of a door opened or closed per second.
+const DOOR_SPEED: f32 = 1.2;
+const DOOR_REACH: f32 = 1.2;
+
+pub struct Door {
+    pub x: usize,
+    pub y: usize,
+    pub open: f32,
+    pub opening: bool,
+    pub horizontal: bool,
+}
+
+pub struct Doors {
+    doors: Vec<Door>,
+}
+
+impl Doors {
+    pub fn from_maze(maze: &Maze) -> Self {
+        let mut doors = Vec::new();
+        for (y, row) in maze.rows().iter().enumerate() {
+            for (x, &cell) in row.iter().enumerate() {
+                if cell != 'D' {
+                    continue;
+                }
+                // A door sits in the gap of a wall run: if the run continues
+                // left and right the door panel lies along x, otherwise along y.
+                let left = x > 0 && row[x - 1] != ' ';
+                let right = x + 1 < row.len() && row[x + 1] != ' ';
+                doors.push(Door {
+                    x,
+                    y,
+                    open: 0.0,
+                    opening: false,
+                    horizontal: left && right,
+                });
+            }
+        }
+        Doors { doors }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&Door> {
+        self.doors.iter().find(|door| door.x == x && door.y == y)
+    }
+
+    pub fn is_open(&self, x: usize, y: usize) -> bool {
+        self.get(x, y).is_some_and(|door| door.open >= 1.0)
+    }
+
+    /// Slides every moving door along by `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        for door in self.doors.iter_mut() {
+            if door.opening {
+                door.open = (door.open + DOOR_SPEED * dt).min(1.0);
+            } else {
+                door.open = (door.open - DOOR_SPEED * dt).max(0.0);
+            }
+        }
+    }
+
+    /// Toggles the door the player is facing, together with any doors next to
+    /// it, so a double-width door opens as one. Returns whether a door moved.
+    pub fn use_door(&mut self, pos: Vec2, angle: f32, block_size: usize) -> bool {
+        let block = block_size as f32;
+        let forward = Vec2::new(angle.cos(), angle.sin());
+        let player_cell = (pos.x as usize / block_size, pos.y as usize / block_size);
+
+        let mut target = None;
+        let mut d = 0.0;
+        while d <= DOOR_REACH * block {
+            let point = pos + forward * d;
+            let cell = (point.x as usize / block_size, point.y as usize / block_size);
+            if self.get(cell.0, cell.1).is_some() {
+                target = Some(cell);
+                break;
+            }
+            d += block / 4.0;
+        }
+
+        let Some((x, y)) = target else {
+            return false;
+        };
+
+        let mut group = vec![(x, y)];
+        let mut i = 0;
+        while i < group.len() {
+            let (cx, cy) = group[i];
+            let neighbors = [
+                (cx.wrapping_sub(1), cy),
+                (cx + 1, cy),
+                (cx, cy.wrapping_sub(1)),
+                (cx, cy + 1),
+            ];
+            for neighbor in neighbors {
+                if self.get(neighbor.0, neighbor.1).is_some() && !group.contains(&neighbor) {
+                    group.push(neighbor);
+                }
+            }
+            i += 1;
+        }
+
+        let opening = !self.get(x, y).is_some_and(|door| door.opening);
+        if !opening && group.contains(&player_cell) {
+            return false;
+        }
+
+        for door in self.doors.iter_mut() {
+            if group.contains(&(door.x, door.y)) {
+                door.opening = opening;
+            }
+        }
+        true
+    }
+}
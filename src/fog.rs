@@ -0,0 +1,79 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FogMode {
+    Off,
+    Linear { start: f32, end: f32 },
+    Exponential { density: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fog {
+    pub mode: FogMode,
+    pub color: u32,
+}
+
+/// Brightness applied to east and west wall faces so corners read even when
+/// both walls share a texture.
+pub const SIDE_SHADE: f32 = 0.7;
+
+impl Default for Fog {
+    fn default() -> Self {
+        Fog {
+            mode: FogMode::Exponential { density: 0.0012 },
+            color: 0x141414,
+        }
+    }
+}
+
+impl Fog {
+    /// How much of the fog color replaces the surface color at `distance`
+    /// world units, from 0.0 (clear) to 1.0 (fully fogged).
+    pub fn factor(&self, distance: f32) -> f32 {
+        match self.mode {
+            FogMode::Off => 0.0,
+            // With no band to fade across, fog starts all at once at `start`.
+            FogMode::Linear { start, end } if end <= start => (distance >= start) as i32 as f32,
+            FogMode::Linear { start, end } => ((distance - start) / (end - start)).clamp(0.0, 1.0),
+            FogMode::Exponential { density } => 1.0 - (-density * distance).exp(),
+        }
+    }
+
+    pub fn apply(&self, color: u32, distance: f32) -> u32 {
+        let factor = self.factor(distance);
+        if factor <= 0.0 {
+            return color;
+        }
+        mix(color, self.color, factor)
+    }
+}
+
+pub fn shade(color: u32, brightness: f32) -> u32 {
+    mix(0x000000, color, brightness)
+}
+
+fn mix(from: u32, to: u32, t: f32) -> u32 {
+    let channel = |shift: u32| {
+        let a = ((from >> shift) & 0xFF) as f32;
+        let b = ((to >> shift) & 0xFF) as f32;
+        ((a + (b - a) * t) as u32).min(0xFF) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_fog_with_equal_start_and_end_is_a_hard_edge() {
+        let fog = Fog {
+            mode: FogMode::Linear {
+                start: 500.0,
+                end: 500.0,
+            },
+            color: 0x141414,
+        };
+        assert_eq!(fog.factor(499.0), 0.0);
+        assert_eq!(fog.factor(500.0), 1.0);
+        assert_eq!(fog.apply(0xFFFFFF, 800.0), 0x141414);
+    }
+}
@@ -36,4 +36,13 @@ impl Framebuffer {
     pub fn set_current_color(&mut self, color: u32) {
         self.current_color = color;
     }
+
+    /// Writes the buffer out as an RGB PNG, dropping the unused high byte.
+    pub fn save_png(&self, path: &str) -> image::ImageResult<()> {
+        let image = image::RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let color = self.buffer[y as usize * self.width + x as usize];
+            image::Rgb([(color >> 16) as u8, (color >> 8) as u8, color as u8])
+        });
+        image.save(path)
+    }
 }
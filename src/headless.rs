@@ -0,0 +1,87 @@
+use std::error::Error;
+
+use crate::door::Doors;
+use crate::framebuffer::Framebuffer;
+use crate::maze::{cell_center, Layers, Maze};
+use crate::player::Player;
+use crate::renderer::Renderer;
+
+/// Renders a single 3D frame of `maze_file` as seen by `player`, with the
+/// map's enemies in place, and writes it to `path`. No window or audio
+/// device is opened, so this works on machines without a display.
+pub fn render_to_png(
+    maze_file: &str,
+    player: &Player,
+    width: usize,
+    height: usize,
+    path: &str,
+) -> Result<(), Box<dyn Error>> {
+    let block_size = 100;
+    let maze = Maze::load(maze_file)?;
+    let layers = Layers::load(maze_file);
+    let doors = Doors::from_maze(&maze);
+    let enemies: Vec<_> = maze
+        .enemies()
+        .iter()
+        .map(|&cell| cell_center(cell, block_size))
+        .collect();
+    let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+
+    let mut framebuffer = Framebuffer::new(width, height);
+    let mut renderer = Renderer::new(width, height, threads, Default::default())?;
+    renderer.set_pixel_depth(!layers.height.is_empty());
+    renderer.set_wall_textures(maze.textures())?;
+    if let Some(blocks) = maze.view_distance() {
+        renderer.view_distance = blocks * block_size as f32;
+    }
+
+    renderer.render3d(&mut framebuffer, player, &maze, &doors, &layers, block_size);
+    renderer.render_enemies(&mut framebuffer, player, &enemies);
+    framebuffer.save_png(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+    use std::f32::consts::PI;
+
+    const MAP: &str = "tests/fixtures/golden/room.txt";
+    const REFERENCE: &str = "tests/fixtures/golden/room.png";
+
+    /// Renders the fixture room and compares it with the reference image.
+    /// Run with `UPDATE_GOLDEN=1` to rewrite the reference after an
+    /// intended change to the renderer.
+    #[test]
+    fn matches_reference_png() {
+        let player = Player {
+            pos: Vec2::new(150.0, 350.0),
+            angle: 10f32.to_radians(),
+            fov: PI / 3.0,
+            pitch: 0.0,
+        };
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            render_to_png(MAP, &player, 320, 200, REFERENCE).unwrap();
+        }
+
+        let path =
+            std::env::temp_dir().join(format!("raycaster-golden-{}.png", std::process::id()));
+        render_to_png(MAP, &player, 320, 200, path.to_str().unwrap()).unwrap();
+        let rendered = image::open(&path).unwrap().to_rgb8();
+        std::fs::remove_file(&path).unwrap();
+        let reference = image::open(REFERENCE).unwrap().to_rgb8();
+
+        assert_eq!(rendered.dimensions(), reference.dimensions());
+        let differing = rendered
+            .pixels()
+            .zip(reference.pixels())
+            .filter(|(a, b)| a != b)
+            .count();
+        assert_eq!(
+            differing, 0,
+            "{} pixels differ from {}",
+            differing, REFERENCE
+        );
+    }
+}
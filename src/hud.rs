@@ -0,0 +1,125 @@
+use crate::framebuffer::Framebuffer;
+use crate::maze::Maze;
+use crate::player::Player;
+
+const SCALE: usize = 5;
+
+pub fn draw_text(framebuffer: &mut Framebuffer, text: &str, x: usize, y: usize) {
+    let mut cursor_x = x;
+
+    for ch in text.chars() {
+        draw_char(framebuffer, ch.to_ascii_uppercase(), cursor_x, y);
+        cursor_x += 4 * SCALE + 10;
+    }
+}
+
+fn draw_char(framebuffer: &mut Framebuffer, ch: char, x: usize, y: usize) {
+    let bitmap = match ch {
+        '0' => [0x7E, 0x99, 0x91, 0x89, 0x7E],
+        '1' => [0x00, 0x00, 0xFF, 0x00, 0x00],
+        '2' => [0xE2, 0x91, 0x91, 0x91, 0x8E],
+        '3' => [0x42, 0x81, 0x89, 0x89, 0x76],
+        '4' => [0x18, 0x14, 0x92, 0xFF, 0x90],
+        '5' => [0x4F, 0x89, 0x89, 0x89, 0x71],
+        '6' => [0x7E, 0x89, 0x89, 0x89, 0x72],
+        '7' => [0x01, 0xE1, 0x11, 0x09, 0x07],
+        '8' => [0x76, 0x89, 0x89, 0x89, 0x76],
+        '9' => [0x4E, 0x91, 0x91, 0x91, 0x7E],
+        'A' => [0x7E, 0x11, 0x11, 0x11, 0x7E],
+        'B' => [0xFF, 0x89, 0x89, 0x89, 0x76],
+        'C' => [0x7E, 0x81, 0x81, 0x81, 0x42],
+        'D' => [0xFF, 0x81, 0x81, 0x42, 0x3C],
+        'E' => [0xFF, 0x91, 0x91, 0x91, 0x81],
+        'F' => [0xFF, 0x09, 0x09, 0x01, 0x00],
+        'G' => [0x7E, 0x81, 0x91, 0x91, 0x72],
+        'H' => [0xFF, 0x08, 0x08, 0x08, 0xFF],
+        'I' => [0x81, 0x81, 0xFF, 0x81, 0x81],
+        'J' => [0x40, 0x80, 0x81, 0x7F, 0x01],
+        'K' => [0xFF, 0x08, 0x14, 0x22, 0xC1],
+        'N' => [0xFF, 0x10, 0x20, 0x40, 0xFF],
+        'P' => [0xFF, 0x11, 0x11, 0x11, 0x0E],
+        'Q' => [0x7E, 0x81, 0xA1, 0x41, 0xBE],
+        'R' => [0xFF, 0x19, 0x29, 0x49, 0x86],
+        'S' => [0x8E, 0x91, 0x91, 0x91, 0x62],
+        'T' => [0x01, 0x01, 0xFF, 0x01, 0x01],
+        'U' => [0x7F, 0x80, 0x80, 0x80, 0x7F],
+        'V' => [0x1F, 0x60, 0x80, 0x60, 0x1F],
+        'W' => [0xFF, 0x40, 0x20, 0x40, 0xFF],
+        'X' => [0xC3, 0x24, 0x18, 0x24, 0xC3],
+        'M' => [0xFF, 0x02, 0x04, 0x02, 0xFF],
+        'O' => [0x7E, 0x81, 0x81, 0x81, 0x7E],
+        'Y' => [0x07, 0x08, 0xF0, 0x08, 0x07],
+        'Z' => [0xC1, 0xA1, 0x91, 0x89, 0x87],
+        'L' => [0xFF, 0x80, 0x80, 0x80, 0x80],
+        '!' => [0x00, 0x00, 0xCF, 0x00, 0x00],
+        '-' => [0x08, 0x08, 0x08, 0x08, 0x08],
+        '+' => [0x08, 0x08, 0x3E, 0x08, 0x08],
+        ':' => [0x00, 0x00, 0x66, 0x00, 0x00],
+        ',' => [0x00, 0x80, 0x60, 0x00, 0x00],
+        '>' => [0x81, 0x42, 0x24, 0x18, 0x00],
+        '\'' => [0x00, 0x07, 0x00, 0x00, 0x00],
+        ' ' => [0x00, 0x00, 0x00, 0x00, 0x00],
+        _ => [0x00, 0x00, 0x00, 0x00, 0x00],
+    };
+
+    for (i, &byte) in bitmap.iter().enumerate() {
+        for bit in 0..8 {
+            if byte & (1 << bit) != 0 {
+                draw_pixel(framebuffer, x + i * SCALE, y + bit * SCALE, SCALE);
+            }
+        }
+    }
+}
+
+fn draw_pixel(framebuffer: &mut Framebuffer, x: usize, y: usize, scale: usize) {
+    for dx in 0..scale {
+        for dy in 0..scale {
+            framebuffer.set_current_color(0xFFFFFF);
+            framebuffer.point(x + dx, y + dy);
+        }
+    }
+}
+
+pub fn draw_minimap(
+    framebuffer: &mut Framebuffer,
+    player: &Player,
+    maze: &Maze,
+    minimap_scale: usize,
+    minimap_x: usize,
+    minimap_y: usize,
+    block_size: usize,
+) {
+    for (y, row) in maze.rows().iter().enumerate() {
+        for (x, &cell) in row.iter().enumerate() {
+            let color = match cell {
+                '+' | '-' | '|' => 0x333333,
+                ' ' => 0xAAAAAA,
+                'g' => 0xFF0000,
+                'D' => 0x8B5A2B,
+                _ => 0x333333,
+            };
+            for dy in 0..minimap_scale {
+                for dx in 0..minimap_scale {
+                    framebuffer.set_current_color(color);
+                    framebuffer.point(
+                        minimap_x + x * minimap_scale + dx,
+                        minimap_y + y * minimap_scale + dy,
+                    );
+                }
+            }
+        }
+    }
+
+    let player_x = (player.pos.x as usize * minimap_scale / block_size) + minimap_x;
+    let player_y = (player.pos.y as usize * minimap_scale / block_size) + minimap_y;
+    let player_size = 6;
+    framebuffer.set_current_color(0xFF0000);
+    for dx in 0..player_size {
+        for dy in 0..player_size {
+            framebuffer.point(
+                player_x + dx - player_size / 2,
+                player_y + dy - player_size / 2,
+            );
+        }
+    }
+}
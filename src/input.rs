@@ -0,0 +1,634 @@
+use gilrs::{Axis, Button, EventType, Gilrs};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+use crate::cursor;
+
+/// Stick deflection below which an axis binding reads as released.
+const DEAD_ZONE: f32 = 0.5;
+/// Stick deflection needed to pick an axis on the rebinding screen, high
+/// enough that a resting stick doesn't grab the binding.
+const CAPTURE_THRESHOLD: f32 = 0.75;
+
+/// Everything the player can do, each bound to any number of keys, mouse
+/// buttons and gamepad inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    StrafeLeft,
+    StrafeRight,
+    TurnLeft,
+    TurnRight,
+    Use,
+    ToggleMap,
+    ToggleFog,
+    Pause,
+    Confirm,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 12] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::StrafeLeft,
+        Action::StrafeRight,
+        Action::TurnLeft,
+        Action::TurnRight,
+        Action::Use,
+        Action::ToggleMap,
+        Action::ToggleFog,
+        Action::Pause,
+        Action::Confirm,
+        Action::Quit,
+    ];
+
+    /// The name shown on the rebinding screen.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::MoveForward => "forward",
+            Action::MoveBackward => "backward",
+            Action::StrafeLeft => "strafe left",
+            Action::StrafeRight => "strafe right",
+            Action::TurnLeft => "turn left",
+            Action::TurnRight => "turn right",
+            Action::Use => "use",
+            Action::ToggleMap => "map",
+            Action::ToggleFog => "fog",
+            Action::Pause => "pause",
+            Action::Confirm => "confirm",
+            Action::Quit => "quit",
+        }
+    }
+}
+
+/// One physical input. In config files it is written as the key name
+/// (`"W"`, `"Space"`, `"Up"`), `"Mouse Left"`, a gamepad button such as
+/// `"Pad South"`, or one direction of a gamepad axis such as
+/// `"Pad LeftStickY+"`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Binding {
+    Key(Key),
+    Mouse(MouseButton),
+    Button(Button),
+    Axis { axis: Axis, positive: bool },
+}
+
+const KEYS: [Key; 106] = [
+    Key::Key0,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+    Key::G,
+    Key::H,
+    Key::I,
+    Key::J,
+    Key::K,
+    Key::L,
+    Key::M,
+    Key::N,
+    Key::O,
+    Key::P,
+    Key::Q,
+    Key::R,
+    Key::S,
+    Key::T,
+    Key::U,
+    Key::V,
+    Key::W,
+    Key::X,
+    Key::Y,
+    Key::Z,
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F13,
+    Key::F14,
+    Key::F15,
+    Key::Down,
+    Key::Left,
+    Key::Right,
+    Key::Up,
+    Key::Apostrophe,
+    Key::Backquote,
+    Key::Backslash,
+    Key::Comma,
+    Key::Equal,
+    Key::LeftBracket,
+    Key::Minus,
+    Key::Period,
+    Key::RightBracket,
+    Key::Semicolon,
+    Key::Slash,
+    Key::Backspace,
+    Key::Delete,
+    Key::End,
+    Key::Enter,
+    Key::Escape,
+    Key::Home,
+    Key::Insert,
+    Key::Menu,
+    Key::PageDown,
+    Key::PageUp,
+    Key::Pause,
+    Key::Space,
+    Key::Tab,
+    Key::NumLock,
+    Key::CapsLock,
+    Key::ScrollLock,
+    Key::LeftShift,
+    Key::RightShift,
+    Key::LeftCtrl,
+    Key::RightCtrl,
+    Key::NumPad0,
+    Key::NumPad1,
+    Key::NumPad2,
+    Key::NumPad3,
+    Key::NumPad4,
+    Key::NumPad5,
+    Key::NumPad6,
+    Key::NumPad7,
+    Key::NumPad8,
+    Key::NumPad9,
+    Key::NumPadDot,
+    Key::NumPadSlash,
+    Key::NumPadAsterisk,
+    Key::NumPadMinus,
+    Key::NumPadPlus,
+    Key::NumPadEnter,
+    Key::LeftAlt,
+    Key::RightAlt,
+    Key::LeftSuper,
+    Key::RightSuper,
+];
+
+const MOUSE_BUTTONS: [MouseButton; 3] =
+    [MouseButton::Left, MouseButton::Middle, MouseButton::Right];
+
+const BUTTONS: [Button; 17] = [
+    Button::South,
+    Button::East,
+    Button::North,
+    Button::West,
+    Button::LeftTrigger,
+    Button::LeftTrigger2,
+    Button::RightTrigger,
+    Button::RightTrigger2,
+    Button::Select,
+    Button::Start,
+    Button::Mode,
+    Button::LeftThumb,
+    Button::RightThumb,
+    Button::DPadUp,
+    Button::DPadDown,
+    Button::DPadLeft,
+    Button::DPadRight,
+];
+
+const AXES: [Axis; 8] = [
+    Axis::LeftStickX,
+    Axis::LeftStickY,
+    Axis::LeftZ,
+    Axis::RightStickX,
+    Axis::RightStickY,
+    Axis::RightZ,
+    Axis::DPadX,
+    Axis::DPadY,
+];
+
+/// Finds the entry of `options` whose debug name is `name`, ignoring case.
+fn by_name<T: Copy + fmt::Debug>(options: &[T], name: &str) -> Option<T> {
+    options
+        .iter()
+        .copied()
+        .find(|option| format!("{:?}", option).eq_ignore_ascii_case(name))
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{:?}", key),
+            Binding::Mouse(button) => write!(f, "Mouse {:?}", button),
+            Binding::Button(button) => write!(f, "Pad {:?}", button),
+            Binding::Axis { axis, positive } => {
+                write!(f, "Pad {:?}{}", axis, if *positive { '+' } else { '-' })
+            }
+        }
+    }
+}
+
+impl FromStr for Binding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let unknown = || format!("unknown input {:?}", s);
+        let (device, name) = s.trim().split_once(' ').unwrap_or(("", s.trim()));
+        match device.to_lowercase().as_str() {
+            "" => by_name(&KEYS, name).map(Binding::Key),
+            "mouse" => by_name(&MOUSE_BUTTONS, name).map(Binding::Mouse),
+            "pad" => {
+                let axis = |sign: char| {
+                    let axis = by_name(&AXES, name.strip_suffix(sign)?)?;
+                    Some(Binding::Axis {
+                        axis,
+                        positive: sign == '+',
+                    })
+                };
+                by_name(&BUTTONS, name)
+                    .map(Binding::Button)
+                    .or_else(|| axis('+'))
+                    .or_else(|| axis('-'))
+            }
+            _ => None,
+        }
+        .ok_or_else(unknown)
+    }
+}
+
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Binding> for String {
+    fn from(binding: Binding) -> String {
+        binding.to_string()
+    }
+}
+
+impl Binding {
+    fn is_gamepad(&self) -> bool {
+        matches!(self, Binding::Button(_) | Binding::Axis { .. })
+    }
+
+    /// How far the input is held, from 0.0 to 1.0. Keys and buttons are
+    /// either; sticks give the deflection past the dead zone.
+    fn value(&self, window: &Window, gilrs: &Gilrs) -> f32 {
+        match *self {
+            Binding::Key(key) => window.is_key_down(key) as i32 as f32,
+            Binding::Mouse(button) => window.get_mouse_down(button) as i32 as f32,
+            Binding::Button(button) => gilrs
+                .gamepads()
+                .any(|(_, gamepad)| gamepad.is_pressed(button))
+                as i32 as f32,
+            Binding::Axis { axis, positive } => gilrs
+                .gamepads()
+                .map(|(_, gamepad)| {
+                    let value = gamepad.value(axis);
+                    if positive {
+                        value
+                    } else {
+                        -value
+                    }
+                })
+                .filter(|&value| value > DEAD_ZONE)
+                .fold(0.0, f32::max),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "failed to read controls: {}", err),
+            ConfigError::Parse(err) => write!(f, "invalid controls file: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(err) => Some(err),
+            ConfigError::Parse(err) => Some(err),
+        }
+    }
+}
+
+/// The inputs bound to each action, written in config files as a list of
+/// inputs per action. Actions the file leaves out keep their default
+/// bindings.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Bindings(BTreeMap<Action, Vec<Binding>>);
+
+impl Default for Bindings {
+    fn default() -> Self {
+        use Binding::{Axis as Stick, Button as Pad, Key as K};
+        let stick = |axis, positive| Stick { axis, positive };
+        Bindings(BTreeMap::from([
+            (
+                Action::MoveForward,
+                vec![
+                    K(Key::W),
+                    K(Key::Up),
+                    Pad(Button::DPadUp),
+                    stick(Axis::LeftStickY, true),
+                ],
+            ),
+            (
+                Action::MoveBackward,
+                vec![
+                    K(Key::S),
+                    K(Key::Down),
+                    Pad(Button::DPadDown),
+                    stick(Axis::LeftStickY, false),
+                ],
+            ),
+            (
+                Action::StrafeLeft,
+                vec![K(Key::A), stick(Axis::LeftStickX, false)],
+            ),
+            (
+                Action::StrafeRight,
+                vec![K(Key::D), stick(Axis::LeftStickX, true)],
+            ),
+            (
+                Action::TurnLeft,
+                vec![
+                    K(Key::Q),
+                    K(Key::Left),
+                    Pad(Button::DPadLeft),
+                    stick(Axis::RightStickX, false),
+                ],
+            ),
+            (
+                Action::TurnRight,
+                vec![
+                    K(Key::E),
+                    K(Key::Right),
+                    Pad(Button::DPadRight),
+                    stick(Axis::RightStickX, true),
+                ],
+            ),
+            (Action::Use, vec![K(Key::Space), Pad(Button::South)]),
+            (Action::ToggleMap, vec![K(Key::M)]),
+            (Action::ToggleFog, vec![K(Key::F)]),
+            (Action::Pause, vec![K(Key::P), Pad(Button::Start)]),
+            (Action::Confirm, vec![K(Key::Enter), Pad(Button::South)]),
+            (Action::Quit, vec![K(Key::Escape), Pad(Button::Select)]),
+        ]))
+    }
+}
+
+impl Bindings {
+    pub fn get(&self, action: Action) -> &[Binding] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Binds `binding` to `action` in place of the action's other inputs on
+    /// the same kind of device, so rebinding a key keeps the gamepad
+    /// binding and the other way round.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let inputs = self.0.entry(action).or_default();
+        inputs.retain(|other| other.is_gamepad() != binding.is_gamepad());
+        inputs.push(binding);
+    }
+}
+
+/// How the mouse turns the view.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MouseSettings {
+    /// Multiplies how far the view turns for each pixel the mouse moves.
+    pub sensitivity: f32,
+    /// Looks up when the mouse moves down.
+    pub invert_y: bool,
+    /// Hides the pointer and keeps it in the middle of the window while
+    /// playing, so turning never stops at the edge of the screen. Only
+    /// works on X11; on Windows, macOS and Wayland the pointer stays
+    /// visible and free whatever this is set to.
+    pub capture: bool,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        MouseSettings {
+            sensitivity: 1.0,
+            invert_y: false,
+            capture: true,
+        }
+    }
+}
+
+/// Everything in the controls file. It is a TOML table from action to a
+/// list of inputs, with the mouse settings in a table of their own:
+///
+/// ```toml
+/// move_forward = ["W", "Up", "Pad DPadUp", "Pad LeftStickY+"]
+/// use = ["Space", "Mouse Left", "Pad South"]
+///
+/// [mouse]
+/// sensitivity = 1.5
+/// invert_y = true
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub mouse: MouseSettings,
+    #[serde(flatten)]
+    pub bindings: Bindings,
+}
+
+impl Config {
+    /// Reads the config from `filename`, or the defaults if it doesn't
+    /// exist.
+    pub fn load(filename: &str) -> Result<Self, ConfigError> {
+        let text = match fs::read_to_string(filename) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => return Err(ConfigError::Io(err)),
+        };
+        let Config { mouse, bindings } = toml::from_str(&text).map_err(ConfigError::Parse)?;
+        let mut config = Config {
+            mouse,
+            ..Config::default()
+        };
+        config.bindings.0.extend(bindings.0);
+        Ok(config)
+    }
+
+    pub fn save(&self, filename: &str) -> io::Result<()> {
+        let text = toml::to_string(self).expect("config always serializes");
+        fs::write(filename, text)
+    }
+}
+
+/// Action state for the current frame, read from the window and gamepads
+/// through the bindings.
+pub struct Input {
+    pub config: Config,
+    values: BTreeMap<Action, f32>,
+    previous: BTreeMap<Action, f32>,
+    mouse_down: [bool; 3],
+    fresh: Option<Binding>,
+    mouse_position: Option<(f32, f32)>,
+    mouse_motion: (f32, f32),
+    grabbed: bool,
+}
+
+impl Input {
+    pub fn new(config: Config) -> Self {
+        Input {
+            config,
+            values: BTreeMap::new(),
+            previous: BTreeMap::new(),
+            mouse_down: [false; 3],
+            fresh: None,
+            mouse_position: None,
+            mouse_motion: (0.0, 0.0),
+            grabbed: false,
+        }
+    }
+
+    /// Samples every action. Call once per frame before reading any state;
+    /// this also drains the gamepad event queue.
+    pub fn update(&mut self, window: &Window, gilrs: &mut Gilrs) {
+        self.fresh = window
+            .get_keys_pressed(KeyRepeat::No)
+            .first()
+            .map(|&key| Binding::Key(key));
+
+        // Motion only counts while the pointer stays in the window, so it
+        // doesn't jump by however far the pointer went outside it.
+        let position = window.get_unscaled_mouse_pos(MouseMode::Discard);
+        self.mouse_motion = match (self.mouse_position, position) {
+            (Some((x0, y0)), Some((x1, y1))) => (x1 - x0, y1 - y0),
+            _ => (0.0, 0.0),
+        };
+        self.mouse_position = position;
+
+        for (down, button) in self.mouse_down.iter_mut().zip(MOUSE_BUTTONS) {
+            let now = window.get_mouse_down(button);
+            if now && !*down && self.fresh.is_none() {
+                self.fresh = Some(Binding::Mouse(button));
+            }
+            *down = now;
+        }
+
+        while let Some(event) = gilrs.next_event() {
+            let binding = match event.event {
+                EventType::ButtonPressed(button, _) if button != Button::Unknown => {
+                    Binding::Button(button)
+                }
+                EventType::AxisChanged(axis, value, _)
+                    if axis != Axis::Unknown && value.abs() > CAPTURE_THRESHOLD =>
+                {
+                    Binding::Axis {
+                        axis,
+                        positive: value > 0.0,
+                    }
+                }
+                _ => continue,
+            };
+            self.fresh.get_or_insert(binding);
+        }
+
+        std::mem::swap(&mut self.previous, &mut self.values);
+        for action in Action::ALL {
+            let value = self
+                .config
+                .bindings
+                .get(action)
+                .iter()
+                .map(|binding| binding.value(window, gilrs))
+                .fold(0.0, f32::max);
+            self.values.insert(action, value.min(1.0));
+        }
+    }
+
+    /// How far `action` is held, from 0.0 to 1.0.
+    pub fn value(&self, action: Action) -> f32 {
+        self.values.get(&action).copied().unwrap_or(0.0)
+    }
+
+    pub fn down(&self, action: Action) -> bool {
+        self.value(action) > 0.0
+    }
+
+    /// Whether `action` went down this frame.
+    pub fn pressed(&self, action: Action) -> bool {
+        self.down(action) && self.previous.get(&action).is_none_or(|&value| value == 0.0)
+    }
+
+    /// `positive` minus `negative`, for actions that pull opposite ways.
+    pub fn axis(&self, positive: Action, negative: Action) -> f32 {
+        self.value(positive) - self.value(negative)
+    }
+
+    /// The first key, mouse button or gamepad input newly pressed this
+    /// frame, whatever it is bound to.
+    pub fn fresh(&self) -> Option<Binding> {
+        self.fresh
+    }
+
+    /// How far the mouse moved since the last frame, in window pixels.
+    pub fn mouse_motion(&self) -> (f32, f32) {
+        self.mouse_motion
+    }
+
+    /// Captures the pointer for mouse look while `grab` is set, the window
+    /// has focus and the config allows it, and lets it go otherwise. Call
+    /// once per frame after `update`. A captured pointer is hidden and put
+    /// back in the middle of the window once it wanders out of the middle
+    /// half; where that isn't possible the pointer stays visible and free.
+    pub fn grab_mouse(&mut self, window: &mut Window, grab: bool) {
+        let (width, height) = window.get_size();
+        let center = (width as f32 / 2.0, height as f32 / 2.0);
+        let near_center = self.mouse_position.is_some_and(|(x, y)| {
+            (x - center.0).abs() < center.0 / 2.0 && (y - center.1).abs() < center.1 / 2.0
+        });
+        let grabbed = if !(grab && self.config.mouse.capture && window.is_active()) {
+            false
+        } else if self.grabbed && near_center {
+            true
+        } else if cursor::warp(window, center.0, center.1) {
+            self.mouse_position = Some(center);
+            true
+        } else {
+            false
+        };
+        if grabbed != self.grabbed {
+            window.set_cursor_visibility(!grabbed);
+            self.grabbed = grabbed;
+        }
+    }
+}
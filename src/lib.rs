@@ -0,0 +1,20 @@
+//! The raycasting engine behind the game: map loading, ray casting, player
+//! simulation and the software renderers, all drawing into a `Framebuffer`
+//! so they can run with or without a window.
+
+pub mod caster;
+pub mod cursor;
+pub mod door;
+pub mod fog;
+pub mod framebuffer;
+pub mod headless;
+pub mod hud;
+pub mod input;
+pub mod maze;
+pub mod menu;
+pub mod path;
+pub mod player;
+pub mod renderer;
+pub mod sfx;
+pub mod texture;
+pub mod timestep;
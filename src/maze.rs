@@ -1,26 +1,461 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use crate::door::Doors;
+use nalgebra_glm::Vec2;
+use std::collections::{BTreeMap, VecDeque};
+use std::f32::consts::PI;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::{Path, PathBuf};
 
-pub fn load_maze(filename: &str) -> Vec<Vec<char>> {
-    let file = File::open(filename).unwrap();
+pub mod generate;
+pub mod json;
+pub mod png;
+pub mod tiled;
+pub mod validate;
+
+/// Characters a maze may contain as solid or empty cells.
+const TILES: [char; 6] = [' ', '+', '-', '|', 'g', 'D'];
+
+#[derive(Debug)]
+pub enum MazeError {
+    MissingFile(String),
+    Io(io::Error),
+    Json(serde_json::Error),
+    Tiled(String),
+    Image(image::ImageError),
+    Empty,
+    NoGoal,
+    NoSpawn,
+    UnknownColor { color: u32, x: usize, y: usize },
+    UnknownTile { tile: char, x: usize, y: usize },
+    SpawnInWall { x: usize, y: usize },
+    BadEntity { x: usize, y: usize },
+    UnreachableGoal,
+}
+
+impl fmt::Display for MazeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MazeError::MissingFile(path) => write!(f, "maze file {} not found", path),
+            MazeError::Io(err) => write!(f, "failed to read maze: {}", err),
+            MazeError::Json(err) => write!(f, "invalid JSON map: {}", err),
+            MazeError::Tiled(err) => write!(f, "invalid Tiled map: {}", err),
+            MazeError::Image(err) => write!(f, "invalid map image: {}", err),
+            MazeError::Empty => write!(f, "maze is empty"),
+            MazeError::NoGoal => write!(f, "maze has no goal ('g')"),
+            MazeError::NoSpawn => write!(f, "maze has no spawn ('p', '^', '>', 'v' or '<')"),
+            MazeError::UnknownColor { color, x, y } => {
+                write!(f, "unknown color #{:06X} at column {}, row {}", color, x, y)
+            }
+            MazeError::UnknownTile { tile, x, y } => {
+                write!(f, "unknown tile {:?} at column {}, row {}", tile, x, y)
+            }
+            MazeError::SpawnInWall { x, y } => {
+                write!(f, "spawn at column {}, row {} is inside a wall", x, y)
+            }
+            MazeError::BadEntity { x, y } => {
+                write!(
+                    f,
+                    "entity at column {}, row {} is not on a floor cell",
+                    x, y
+                )
+            }
+            MazeError::UnreachableGoal => write!(f, "the goal cannot be reached from the spawn"),
+        }
+    }
+}
+
+impl std::error::Error for MazeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MazeError::Io(err) => Some(err),
+            MazeError::Json(err) => Some(err),
+            MazeError::Image(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Where the player starts and which way they face, in radians.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spawn {
+    pub cell: (usize, usize),
+    pub angle: f32,
+}
+
+/// Spawn markers and the direction each one faces. `p` faces east.
+fn spawn_angle(marker: char) -> Option<f32> {
+    match marker {
+        'p' | '>' => Some(0.0),
+        'v' => Some(PI / 2.0),
+        '<' => Some(PI),
+        '^' => Some(-PI / 2.0),
+        _ => None,
+    }
+}
+
+/// The spawn marker whose facing is closest to `angle`.
+fn spawn_marker(angle: f32) -> char {
+    let quarter = (angle.rem_euclid(2.0 * PI) / (PI / 2.0)).round() as usize;
+    ['p', 'v', '<', '^'][quarter % 4]
+}
+
+const ENEMY: char = 'e';
+const PICKUP: char = '*';
+
+pub struct Maze {
+    cells: Vec<Vec<char>>,
+    width: usize,
+    height: usize,
+    spawn: Spawn,
+    enemies: Vec<(usize, usize)>,
+    pickups: Vec<(usize, usize)>,
+    textures: BTreeMap<char, String>,
+    metadata: BTreeMap<String, String>,
+}
+
+/// Spawn, entities, texture paths and metadata given alongside the tiles
+/// instead of as markers in them, as the JSON object form does.
+#[derive(Default)]
+pub struct MapExtras {
+    pub spawn: Option<Spawn>,
+    pub enemies: Vec<(usize, usize)>,
+    pub pickups: Vec<(usize, usize)>,
+    pub textures: BTreeMap<char, String>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl Maze {
+    /// Reads the grid at the top of `filename`, up to the first `[layer]`
+    /// section. Files ending in `.json` are read as JSON maps instead, and
+    /// Tiled maps and PNG images are imported.
+    pub fn load(filename: &str) -> Result<Self, MazeError> {
+        if tiled::is_tiled(filename) {
+            return tiled::load(filename);
+        }
+        if png::is_png(filename) {
+            return png::load(filename);
+        }
+        let text = fs::read_to_string(filename).map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => MazeError::MissingFile(filename.to_string()),
+            _ => MazeError::Io(err),
+        })?;
+        if filename.ends_with(".json") {
+            let dir = Path::new(filename).parent().unwrap_or(Path::new(""));
+            return json::parse(&text, dir);
+        }
+        let rows = text
+            .lines()
+            .take_while(|line| !line.starts_with('['))
+            .map(|line| line.chars().collect())
+            .collect();
+        Maze::from_rows(rows)
+    }
+
+    /// Writes the maze to `filename`, as JSON if it ends in `.json` and in
+    /// the text format otherwise.
+    pub fn save(&self, filename: &str) -> Result<(), MazeError> {
+        let text = if filename.ends_with(".json") {
+            let dir = Path::new(filename).parent().unwrap_or(Path::new(""));
+            json::to_string(self, dir)
+        } else {
+            self.to_text()
+        };
+        fs::write(filename, text).map_err(MazeError::Io)
+    }
+
+    /// The grid in the text format, with spawn, enemy and pickup markers
+    /// placed back on their cells.
+    pub fn to_text(&self) -> String {
+        let mut rows = self.cells.clone();
+        for &(x, y) in &self.enemies {
+            rows[y][x] = ENEMY;
+        }
+        for &(x, y) in &self.pickups {
+            rows[y][x] = PICKUP;
+        }
+        let (x, y) = self.spawn.cell;
+        rows[y][x] = spawn_marker(self.spawn.angle);
+
+        let mut text = String::new();
+        for row in rows {
+            text.extend(row);
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Builds a maze from raw rows, padding short rows with floor so every
+    /// row is `width` cells long, and checks the goal can be reached. Spawn,
+    /// enemy and pickup markers are collected and replaced with floor.
+    pub fn from_rows(rows: Vec<Vec<char>>) -> Result<Self, MazeError> {
+        Maze::with_extras(rows, MapExtras::default())
+    }
+
+    /// Like `from_rows`, adding the entities in `extras` to any markers in
+    /// the rows. An explicit spawn takes the place of a spawn marker.
+    pub fn with_extras(mut rows: Vec<Vec<char>>, extras: MapExtras) -> Result<Self, MazeError> {
+        while rows.last().is_some_and(|row| row.iter().all(|&c| c == ' ')) {
+            rows.pop();
+        }
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        if width == 0 {
+            return Err(MazeError::Empty);
+        }
+        for row in rows.iter_mut() {
+            row.resize(width, ' ');
+        }
+
+        let mut spawn = extras.spawn;
+        let mut enemies = Vec::new();
+        let mut pickups = Vec::new();
+        let mut goal = false;
+        for (y, row) in rows.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                if let Some(angle) = spawn_angle(*cell) {
+                    spawn.get_or_insert(Spawn {
+                        cell: (x, y),
+                        angle,
+                    });
+                    *cell = ' ';
+                    continue;
+                }
+                match *cell {
+                    ENEMY => {
+                        enemies.push((x, y));
+                        *cell = ' ';
+                    }
+                    PICKUP => {
+                        pickups.push((x, y));
+                        *cell = ' ';
+                    }
+                    'g' => goal = true,
+                    tile if !TILES.contains(&tile) && !extras.textures.contains_key(&tile) => {
+                        return Err(MazeError::UnknownTile { tile, x, y });
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if !goal {
+            return Err(MazeError::NoGoal);
+        }
+        let spawn = spawn.ok_or(MazeError::NoSpawn)?;
+
+        let floor = |&(x, y): &(usize, usize)| rows.get(y).and_then(|row| row.get(x)) == Some(&' ');
+        if !floor(&spawn.cell) {
+            let (x, y) = spawn.cell;
+            return Err(MazeError::SpawnInWall { x, y });
+        }
+        if let Some((x, y)) = extras
+            .enemies
+            .iter()
+            .chain(extras.pickups.iter())
+            .copied()
+            .find(|cell| !floor(cell))
+        {
+            return Err(MazeError::BadEntity { x, y });
+        }
+        enemies.extend(extras.enemies);
+        pickups.extend(extras.pickups);
+
+        let maze = Maze {
+            height: rows.len(),
+            cells: rows,
+            width,
+            spawn,
+            enemies,
+            pickups,
+            textures: extras.textures,
+            metadata: extras.metadata,
+        };
+        if !maze.goal_reachable() {
+            return Err(MazeError::UnreachableGoal);
+        }
+        Ok(maze)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn spawn(&self) -> Spawn {
+        self.spawn
+    }
+
+    pub fn enemies(&self) -> &[(usize, usize)] {
+        &self.enemies
+    }
+
+    pub fn pickups(&self) -> &[(usize, usize)] {
+        &self.pickups
+    }
+
+    /// Texture files assigned to wall tiles by the map, overriding the
+    /// built-in textures.
+    pub fn textures(&self) -> &BTreeMap<char, String> {
+        &self.textures
+    }
+
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    /// How far the player can see, in blocks, from the `view_distance`
+    /// metadata entry. Without one the view reaches the edge of the map.
+    pub fn view_distance(&self) -> Option<f32> {
+        self.metadata.get("view_distance")?.parse().ok()
+    }
+
+    pub fn rows(&self) -> &[Vec<char>] {
+        &self.cells
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Option<char> {
+        self.cells.get(y).and_then(|row| row.get(x)).copied()
+    }
+
+    pub fn is_blocked(&self, doors: &Doors, x: usize, y: usize) -> bool {
+        match self.cell(x, y) {
+            Some(' ') => false,
+            Some('D') => !doors.is_open(x, y),
+            _ => true,
+        }
+    }
+
+    pub fn is_goal(&self, x: usize, y: usize) -> bool {
+        self.cell(x, y) == Some('g')
+    }
+
+    /// The goal is a wall tile, so it counts as reached once the player can
+    /// stand next to it. Doors are passable here since they can be opened.
+    fn goal_reachable(&self) -> bool {
+        let mut seen = vec![vec![false; self.width]; self.height];
+        let (sx, sy) = self.spawn.cell;
+        let mut queue = VecDeque::from([(sx, sy)]);
+        seen[sy][sx] = true;
+
+        while let Some((x, y)) = queue.pop_front() {
+            let neighbors = [
+                (x.wrapping_sub(1), y),
+                (x + 1, y),
+                (x, y.wrapping_sub(1)),
+                (x, y + 1),
+            ];
+            for (nx, ny) in neighbors {
+                match self.cell(nx, ny) {
+                    Some('g') => return true,
+                    Some(' ') | Some('D') if !seen[ny][nx] => {
+                        seen[ny][nx] = true;
+                        queue.push_back((nx, ny));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        false
+    }
+}
+
+/// `path` as written in a map file in `dir`, which is where relative paths
+/// start from.
+fn relative(dir: &Path, path: &str) -> String {
+    dir.join(path).to_string_lossy().into_owned()
+}
+
+/// The inverse of `relative`: `path` as it should be written in a map file
+/// in `dir` so it still points at the same file.
+fn relative_from(dir: &Path, path: &str) -> String {
+    let path = Path::new(path);
+    if path.is_absolute() != dir.is_absolute() {
+        return path.to_string_lossy().into_owned();
+    }
+    // Climb out of `dir` to the part it shares with `path`, then back down.
+    let shared = dir
+        .components()
+        .zip(path.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut result = PathBuf::new();
+    for _ in dir.components().skip(shared) {
+        result.push("..");
+    }
+    result.extend(path.components().skip(shared));
+    result.to_string_lossy().into_owned()
+}
+
+/// World position of the middle of `cell`.
+pub fn cell_center(cell: (usize, usize), block_size: usize) -> Vec2 {
+    Vec2::new(
+        (cell.0 as f32 + 0.5) * block_size as f32,
+        (cell.1 as f32 + 0.5) * block_size as f32,
+    )
+}
+
+/// Reads the `[name]` section of `filename`. A missing file or section gives
+/// an empty layer, which every lookup treats as the default.
+pub fn load_layer(filename: &str, name: &str) -> Vec<Vec<char>> {
+    let Ok(file) = File::open(filename) else {
+        return Vec::new();
+    };
     let reader = BufReader::new(file);
+    let header = format!("[{}]", name);
 
     reader
         .lines()
-        .map(|line| line.unwrap().chars().collect())
+        .map_while(Result::ok)
+        .skip_while(|line| line.trim_end() != header)
+        .skip(1)
+        .take_while(|line| !line.starts_with('['))
+        .map(|line| line.chars().collect())
         .collect()
 }
 
-pub fn is_blocked(maze: &Vec<Vec<char>>, x: usize, y: usize) -> bool {
-    if y >= maze.len() || x >= maze[y].len() {
-        return true;
+#[derive(Default)]
+pub struct Layers {
+    pub floor: Vec<Vec<char>>,
+    pub ceiling: Vec<Vec<char>>,
+    pub height: Vec<Vec<char>>,
+}
+
+impl Layers {
+    pub fn load(filename: &str) -> Self {
+        if tiled::is_tiled(filename) {
+            return tiled::load_layers(filename).unwrap_or_default();
+        }
+        Layers {
+            floor: load_layer(filename, "floor"),
+            ceiling: load_layer(filename, "ceiling"),
+            height: load_layer(filename, "height"),
+        }
+    }
+
+    /// Wall height in blocks. The height layer holds quarter blocks, so `4`
+    /// is a standard wall, `2` a half wall and `8` a two-level pillar.
+    pub fn wall_height(&self, x: usize, y: usize) -> f32 {
+        match layer_cell(&self.height, x, y).to_digit(10) {
+            Some(quarters) if quarters > 0 => quarters as f32 / 4.0,
+            _ => 1.0,
+        }
+    }
+
+    pub fn max_wall_height(&self) -> f32 {
+        self.height
+            .iter()
+            .flatten()
+            .filter_map(|cell| cell.to_digit(10))
+            .map(|quarters| quarters as f32 / 4.0)
+            .fold(1.0, f32::max)
     }
-    maze[y][x] != ' '
 }
 
-pub fn is_goal(maze: &Vec<Vec<char>>, x: usize, y: usize) -> bool {
-    if y < maze.len() && x < maze[y].len() {
-        return maze[y][x] == 'g';
+pub fn layer_cell(layer: &[Vec<char>], x: usize, y: usize) -> char {
+    if y < layer.len() && x < layer[y].len() {
+        return layer[y][x];
     }
-    false
+    ' '
 }
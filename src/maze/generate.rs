@@ -0,0 +1,425 @@
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    RecursiveBacktracker,
+    Prim,
+    Kruskal,
+    Eller,
+    Wilson,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 5] = [
+        Algorithm::RecursiveBacktracker,
+        Algorithm::Prim,
+        Algorithm::Kruskal,
+        Algorithm::Eller,
+        Algorithm::Wilson,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::RecursiveBacktracker => "backtracker",
+            Algorithm::Prim => "prim",
+            Algorithm::Kruskal => "kruskal",
+            Algorithm::Eller => "eller",
+            Algorithm::Wilson => "wilson",
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Algorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == s)
+            .ok_or_else(|| format!("unknown maze algorithm {:?}", s))
+    }
+}
+
+/// A `width` by `height` grid of cells and the passages carved between them.
+struct Grid {
+    width: usize,
+    height: usize,
+    east: Vec<Vec<bool>>,
+    south: Vec<Vec<bool>>,
+}
+
+impl Grid {
+    fn new(width: usize, height: usize) -> Self {
+        Grid {
+            width,
+            height,
+            east: vec![vec![false; width]; height],
+            south: vec![vec![false; width]; height],
+        }
+    }
+
+    fn neighbors(&self, (x, y): (usize, usize)) -> Vec<(usize, usize)> {
+        let mut cells = Vec::with_capacity(4);
+        if x > 0 {
+            cells.push((x - 1, y));
+        }
+        if x + 1 < self.width {
+            cells.push((x + 1, y));
+        }
+        if y > 0 {
+            cells.push((x, y - 1));
+        }
+        if y + 1 < self.height {
+            cells.push((x, y + 1));
+        }
+        cells
+    }
+
+    /// Opens the wall between two adjacent cells. Passages are stored on the
+    /// west or north cell of the pair.
+    fn carve(&mut self, a: (usize, usize), b: (usize, usize)) {
+        let ((x, y), other) = if a < b { (a, b) } else { (b, a) };
+        if other.1 == y {
+            self.east[y][x] = true;
+        } else {
+            self.south[y][x] = true;
+        }
+    }
+
+    fn connected(&self, a: (usize, usize), b: (usize, usize)) -> bool {
+        let ((x, y), other) = if a < b { (a, b) } else { (b, a) };
+        if other.1 == y {
+            self.east[y][x]
+        } else {
+            self.south[y][x]
+        }
+    }
+
+    /// Breadth-first distances from `start` through carved passages.
+    fn distances(&self, start: (usize, usize)) -> Vec<Vec<usize>> {
+        let mut distance = vec![vec![usize::MAX; self.width]; self.height];
+        distance[start.1][start.0] = 0;
+        let mut queue = VecDeque::from([start]);
+        while let Some(cell) = queue.pop_front() {
+            for next in self.neighbors(cell) {
+                if self.connected(cell, next) && distance[next.1][next.0] == usize::MAX {
+                    distance[next.1][next.0] = distance[cell.1][cell.0] + 1;
+                    queue.push_back(next);
+                }
+            }
+        }
+        distance
+    }
+
+    fn farthest(&self, start: (usize, usize)) -> (usize, usize) {
+        let distance = self.distances(start);
+        let mut best = start;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if distance[y][x] > distance[best.1][best.0] {
+                    best = (x, y);
+                }
+            }
+        }
+        best
+    }
+}
+
+/// Generates a perfect maze of `width` by `height` cells in the same `+--+`
+/// and `|` layout as `maze.txt`, so it can be fed to `Maze::from_rows`. The
+/// spawn and goal sit at the two cells farthest apart, with the spawn facing
+/// its only way out. The same seed always gives the same maze.
+pub fn generate(width: usize, height: usize, algorithm: Algorithm, seed: u64) -> Vec<Vec<char>> {
+    let width = width.max(1);
+    let height = height.max(1);
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut grid = Grid::new(width, height);
+
+    match algorithm {
+        Algorithm::RecursiveBacktracker => backtracker(&mut grid, &mut rng),
+        Algorithm::Prim => prim(&mut grid, &mut rng),
+        Algorithm::Kruskal => kruskal(&mut grid, &mut rng),
+        Algorithm::Eller => eller(&mut grid, &mut rng),
+        Algorithm::Wilson => wilson(&mut grid, &mut rng),
+    }
+
+    // In a tree the cell farthest from any cell is one end of the longest
+    // path, so two searches find both ends.
+    let spawn = grid.farthest((0, 0));
+    let goal = grid.farthest(spawn);
+    render(&grid, spawn, goal)
+}
+
+fn render(grid: &Grid, spawn: (usize, usize), goal: (usize, usize)) -> Vec<Vec<char>> {
+    let mut rows = Vec::with_capacity(grid.height * 2 + 1);
+    for y in 0..grid.height {
+        let mut top = Vec::with_capacity(grid.width * 3 + 1);
+        let mut middle = Vec::with_capacity(grid.width * 3 + 1);
+        for x in 0..grid.width {
+            let open_north = y > 0 && grid.south[y - 1][x];
+            top.extend(if open_north { "+  " } else { "+--" }.chars());
+            let open_west = x > 0 && grid.east[y][x - 1];
+            middle.extend(if open_west { "   " } else { "|  " }.chars());
+        }
+        top.push('+');
+        middle.push('|');
+        rows.push(top);
+        rows.push(middle);
+    }
+    rows.push("+--".repeat(grid.width).chars().chain(['+']).collect());
+
+    let exit = grid
+        .neighbors(spawn)
+        .into_iter()
+        .find(|&next| grid.connected(spawn, next));
+    rows[spawn.1 * 2 + 1][spawn.0 * 3 + 1] = match exit {
+        Some((x, _)) if x > spawn.0 => '>',
+        Some((x, _)) if x < spawn.0 => '<',
+        Some((_, y)) if y < spawn.1 => '^',
+        Some(_) => 'v',
+        None => 'p',
+    };
+    // The goal is a wall tile, so it takes one half of a dead-end cell and
+    // leaves the other half to stand in.
+    let goal_x = if grid.east[goal.1][goal.0] {
+        goal.0 * 3 + 1
+    } else {
+        goal.0 * 3 + 2
+    };
+    rows[goal.1 * 2 + 1][goal_x] = 'g';
+    rows
+}
+
+fn backtracker(grid: &mut Grid, rng: &mut ChaCha8Rng) {
+    let mut visited = vec![vec![false; grid.width]; grid.height];
+    let start = (
+        rng.random_range(0..grid.width),
+        rng.random_range(0..grid.height),
+    );
+    visited[start.1][start.0] = true;
+    let mut stack = vec![start];
+
+    while let Some(&cell) = stack.last() {
+        let unvisited: Vec<_> = grid
+            .neighbors(cell)
+            .into_iter()
+            .filter(|&(x, y)| !visited[y][x])
+            .collect();
+        match unvisited.choose(rng) {
+            Some(&next) => {
+                grid.carve(cell, next);
+                visited[next.1][next.0] = true;
+                stack.push(next);
+            }
+            None => {
+                stack.pop();
+            }
+        }
+    }
+}
+
+fn prim(grid: &mut Grid, rng: &mut ChaCha8Rng) {
+    let mut inside = vec![vec![false; grid.width]; grid.height];
+    let mut frontier = Vec::new();
+    let start = (
+        rng.random_range(0..grid.width),
+        rng.random_range(0..grid.height),
+    );
+    inside[start.1][start.0] = true;
+    frontier.extend(grid.neighbors(start));
+
+    while !frontier.is_empty() {
+        let cell = frontier.swap_remove(rng.random_range(0..frontier.len()));
+        if inside[cell.1][cell.0] {
+            continue;
+        }
+        let (into, out): (Vec<_>, Vec<_>) = grid
+            .neighbors(cell)
+            .into_iter()
+            .partition(|&(x, y)| inside[y][x]);
+        let &from = into.choose(rng).expect("frontier cells touch the maze");
+        grid.carve(from, cell);
+        inside[cell.1][cell.0] = true;
+        frontier.extend(out);
+    }
+}
+
+fn kruskal(grid: &mut Grid, rng: &mut ChaCha8Rng) {
+    let width = grid.width;
+    let index = |(x, y): (usize, usize)| y * width + x;
+    let mut parent: Vec<usize> = (0..grid.width * grid.height).collect();
+
+    let mut edges = Vec::new();
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            if x + 1 < grid.width {
+                edges.push(((x, y), (x + 1, y)));
+            }
+            if y + 1 < grid.height {
+                edges.push(((x, y), (x, y + 1)));
+            }
+        }
+    }
+    edges.shuffle(rng);
+
+    for (a, b) in edges {
+        let (root_a, root_b) = (find(&mut parent, index(a)), find(&mut parent, index(b)));
+        if root_a != root_b {
+            parent[root_a] = root_b;
+            grid.carve(a, b);
+        }
+    }
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+fn eller(grid: &mut Grid, rng: &mut ChaCha8Rng) {
+    let mut sets: Vec<usize> = (0..grid.width).collect();
+    let mut next_set = grid.width;
+
+    for y in 0..grid.height {
+        let last_row = y + 1 == grid.height;
+
+        // Join neighbours in different sets, always on the last row so the
+        // whole maze ends up connected.
+        for x in 0..grid.width - 1 {
+            if sets[x] != sets[x + 1] && (last_row || rng.random_bool(0.5)) {
+                grid.carve((x, y), (x + 1, y));
+                let (keep, merge) = (sets[x], sets[x + 1]);
+                for set in sets.iter_mut().filter(|set| **set == merge) {
+                    *set = keep;
+                }
+            }
+        }
+        if last_row {
+            break;
+        }
+
+        // Every set needs at least one passage down to the next row.
+        let mut below: Vec<Option<usize>> = vec![None; grid.width];
+        let mut start = 0;
+        let mut order: Vec<usize> = (0..grid.width).collect();
+        order.sort_by_key(|&x| sets[x]);
+        while start < order.len() {
+            let set = sets[order[start]];
+            let end = order[start..]
+                .iter()
+                .position(|&x| sets[x] != set)
+                .map_or(order.len(), |len| start + len);
+            let members = &order[start..end];
+            let &forced = members.choose(rng).expect("sets are never empty");
+            for &x in members {
+                if x == forced || rng.random_bool(0.3) {
+                    grid.carve((x, y), (x, y + 1));
+                    below[x] = Some(set);
+                }
+            }
+            start = end;
+        }
+
+        for (x, set) in below.into_iter().enumerate() {
+            sets[x] = set.unwrap_or_else(|| {
+                next_set += 1;
+                next_set
+            });
+        }
+    }
+}
+
+fn wilson(grid: &mut Grid, rng: &mut ChaCha8Rng) {
+    let mut inside = vec![vec![false; grid.width]; grid.height];
+    let first = (
+        rng.random_range(0..grid.width),
+        rng.random_range(0..grid.height),
+    );
+    inside[first.1][first.0] = true;
+    let mut remaining = grid.width * grid.height - 1;
+
+    while remaining > 0 {
+        let mut start;
+        loop {
+            start = (
+                rng.random_range(0..grid.width),
+                rng.random_range(0..grid.height),
+            );
+            if !inside[start.1][start.0] {
+                break;
+            }
+        }
+
+        // Random walk until the maze is hit, erasing loops as they form by
+        // remembering only the last exit taken from each cell.
+        let mut exit = vec![vec![None; grid.width]; grid.height];
+        let mut cell = start;
+        while !inside[cell.1][cell.0] {
+            let &next = grid
+                .neighbors(cell)
+                .choose(rng)
+                .expect("grid has neighbours");
+            exit[cell.1][cell.0] = Some(next);
+            cell = next;
+        }
+
+        let mut cell = start;
+        while !inside[cell.1][cell.0] {
+            let next = exit[cell.1][cell.0].expect("walk left every cell on its path");
+            grid.carve(cell, next);
+            inside[cell.1][cell.0] = true;
+            remaining -= 1;
+            cell = next;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::{validate, Maze};
+
+    #[test]
+    fn same_seed_same_maze() {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(
+                generate(12, 8, algorithm, 42),
+                generate(12, 8, algorithm, 42),
+                "{} is not deterministic",
+                algorithm.name()
+            );
+        }
+        assert_ne!(
+            generate(12, 8, Algorithm::Prim, 1),
+            generate(12, 8, Algorithm::Prim, 2)
+        );
+    }
+
+    #[test]
+    fn every_algorithm_makes_valid_mazes() {
+        for algorithm in Algorithm::ALL {
+            for (width, height, seed) in [(1, 1, 0), (5, 3, 7), (16, 9, 123)] {
+                let rows = generate(width, height, algorithm, seed);
+                let maze = Maze::from_rows(rows).unwrap_or_else(|err| {
+                    panic!("{} {}x{}: {}", algorithm.name(), width, height, err)
+                });
+                assert_eq!(validate::check(&maze), [], "{}", algorithm.name());
+                assert!(validate::solution(&maze).is_some(), "{}", algorithm.name());
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::{relative, relative_from, MapExtras, Maze, MazeError, Spawn};
+
+/// A grid row, either a string or an array of single-character strings.
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum Row {
+    Text(String),
+    Cells(Vec<char>),
+}
+
+impl Row {
+    fn into_cells(self) -> Vec<char> {
+        match self {
+            Row::Text(text) => text.chars().collect(),
+            Row::Cells(cells) => cells,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct MapObject {
+    tiles: Vec<Row>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spawn: Option<SpawnEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    entities: Vec<Entity>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    textures: BTreeMap<char, String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Serialize)]
+struct SpawnEntry {
+    x: usize,
+    y: usize,
+    #[serde(default)]
+    angle: f32,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Entity {
+    kind: EntityKind,
+    x: usize,
+    y: usize,
+}
+
+#[derive(Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum EntityKind {
+    Enemy,
+    Pickup,
+}
+
+/// A map file is either a bare grid, as `maze.py json` used to write, or an
+/// object carrying the grid together with everything placed on it:
+///
+/// ```json
+/// {
+///   "tiles": ["+--+--+", "|    g|", "+--+--+"],
+///   "spawn": { "x": 1, "y": 1, "angle": 0 },
+///   "entities": [{ "kind": "enemy", "x": 2, "y": 1 }],
+///   "textures": { "+": "assets/asset13.png" },
+///   "metadata": { "name": "Tiny" }
+/// }
+/// ```
+///
+/// Rows may be strings or arrays of single-character strings in both forms.
+/// The spawn angle is in degrees, clockwise from east, and texture paths
+/// are relative to `dir`, the directory holding the map file.
+pub fn parse(text: &str, dir: &Path) -> Result<Maze, MazeError> {
+    // Pick the form up front so errors point into it, rather than reporting
+    // that neither form matched.
+    let value: serde_json::Value = serde_json::from_str(text).map_err(MazeError::Json)?;
+    if value.is_array() {
+        let tiles: Vec<Row> = serde_json::from_value(value).map_err(MazeError::Json)?;
+        return Maze::from_rows(tiles.into_iter().map(Row::into_cells).collect());
+    }
+    let object: MapObject = serde_json::from_value(value).map_err(MazeError::Json)?;
+
+    let cells_of = |kind: EntityKind| {
+        object
+            .entities
+            .iter()
+            .filter(|entity| entity.kind == kind)
+            .map(|entity| (entity.x, entity.y))
+            .collect()
+    };
+    let extras = MapExtras {
+        spawn: object.spawn.as_ref().map(|spawn| Spawn {
+            cell: (spawn.x, spawn.y),
+            angle: spawn.angle.to_radians(),
+        }),
+        enemies: cells_of(EntityKind::Enemy),
+        pickups: cells_of(EntityKind::Pickup),
+        textures: object
+            .textures
+            .into_iter()
+            .map(|(tile, path)| (tile, relative(dir, &path)))
+            .collect(),
+        metadata: object.metadata,
+    };
+    let rows = object.tiles.into_iter().map(Row::into_cells).collect();
+    Maze::with_extras(rows, extras)
+}
+
+/// Serializes `maze` in the object form, with the spawn and entities listed
+/// separately from the tiles. Texture paths are written relative to `dir`,
+/// where the file is going.
+pub fn to_string(maze: &Maze, dir: &Path) -> String {
+    let spawn = maze.spawn();
+    let entities = maze
+        .enemies()
+        .iter()
+        .map(|&cell| (EntityKind::Enemy, cell))
+        .chain(
+            maze.pickups()
+                .iter()
+                .map(|&cell| (EntityKind::Pickup, cell)),
+        )
+        .map(|(kind, (x, y))| Entity { kind, x, y })
+        .collect();
+
+    let object = MapObject {
+        tiles: maze
+            .rows()
+            .iter()
+            .map(|row| Row::Text(row.iter().collect()))
+            .collect(),
+        spawn: Some(SpawnEntry {
+            x: spawn.cell.0,
+            y: spawn.cell.1,
+            angle: spawn.angle.to_degrees(),
+        }),
+        entities,
+        textures: maze
+            .textures()
+            .iter()
+            .map(|(&tile, path)| (tile, relative_from(dir, path)))
+            .collect(),
+        metadata: maze.metadata().clone(),
+    };
+    serde_json::to_string_pretty(&object).expect("map objects always serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const MAP: &str = r#"{
+        "tiles": ["+-+", "|p|", "|g|", "+-+"],
+        "textures": { "+": "t.png", "-": "walls/u.png" }
+    }"#;
+
+    fn saved_textures(filename: &Path) -> serde_json::Value {
+        let text = fs::read_to_string(filename).unwrap();
+        serde_json::from_str::<serde_json::Value>(&text).unwrap()["textures"].clone()
+    }
+
+    #[test]
+    fn texture_paths_survive_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("raycaster-json-{}", std::process::id()));
+        let maps = dir.join("maps");
+        fs::create_dir_all(&maps).unwrap();
+        let filename = maps.join("m.json");
+        fs::write(&filename, MAP).unwrap();
+        let filename = filename.to_str().unwrap();
+
+        let maze = Maze::load(filename).unwrap();
+        assert_eq!(maze.textures()[&'+'], maps.join("t.png").to_str().unwrap());
+        for _ in 0..2 {
+            Maze::load(filename).unwrap().save(filename).unwrap();
+        }
+        let textures = saved_textures(Path::new(filename));
+        assert_eq!(textures["+"], "t.png");
+        assert_eq!(textures["-"], "walls/u.png");
+        assert_eq!(Maze::load(filename).unwrap().textures(), maze.textures());
+
+        // Saved elsewhere, the paths still lead back to the same files.
+        let copy = dir.join("other").join("m.json");
+        fs::create_dir_all(copy.parent().unwrap()).unwrap();
+        maze.save(copy.to_str().unwrap()).unwrap();
+        assert_eq!(saved_textures(&copy)["+"], "../maps/t.png");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,56 @@
+use image::{ImageReader, Rgba};
+
+use super::{Maze, MazeError, ENEMY, PICKUP};
+
+/// Pixel colours and the map characters they stand for. Spawn, enemy and
+/// pickup colours become the same markers the text format uses.
+pub const PALETTE: [(u32, char); 9] = [
+    (0xFFFFFF, ' '),
+    (0x000000, '+'),
+    (0x404040, '-'),
+    (0x808080, '|'),
+    (0xFF0000, 'g'),
+    (0x8B5A2B, 'D'),
+    (0x00FF00, 'p'),
+    (0x0000FF, ENEMY),
+    (0xFFFF00, PICKUP),
+];
+
+pub fn is_png(filename: &str) -> bool {
+    filename.to_lowercase().ends_with(".png")
+}
+
+/// Reads a map drawn as an image, one pixel per cell, with colours taken
+/// from `PALETTE`. Transparent pixels are floor. The player starts on the
+/// green pixel facing east.
+pub fn load(filename: &str) -> Result<Maze, MazeError> {
+    let image = ImageReader::open(filename)
+        .map_err(|err| match err.kind() {
+            std::io::ErrorKind::NotFound => MazeError::MissingFile(filename.to_string()),
+            _ => MazeError::Io(err),
+        })?
+        .decode()
+        .map_err(MazeError::Image)?
+        .to_rgba8();
+
+    let rows = image
+        .rows()
+        .enumerate()
+        .map(|(y, row)| {
+            row.enumerate()
+                .map(|(x, &Rgba([r, g, b, a]))| {
+                    if a < 128 {
+                        return Ok(' ');
+                    }
+                    let color = (r as u32) << 16 | (g as u32) << 8 | b as u32;
+                    PALETTE
+                        .iter()
+                        .find(|&&(entry, _)| entry == color)
+                        .map(|&(_, cell)| cell)
+                        .ok_or(MazeError::UnknownColor { color, x, y })
+                })
+                .collect()
+        })
+        .collect::<Result<_, _>>()?;
+    Maze::from_rows(rows)
+}
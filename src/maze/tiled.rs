@@ -0,0 +1,579 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::{relative, MapExtras, Maze, MazeError, Spawn, TILES};
+use crate::maze::Layers;
+
+type Properties = HashMap<String, String>;
+
+/// The parts of a Tiled map the importer uses, read from either TMX or JSON.
+struct TiledMap {
+    width: usize,
+    height: usize,
+    tile_width: f32,
+    tile_height: f32,
+    tile_layers: Vec<TileLayer>,
+    objects: Vec<Object>,
+    tiles: HashMap<u32, Tile>,
+}
+
+struct TileLayer {
+    name: String,
+    gids: Vec<u32>,
+}
+
+struct Object {
+    kind: String,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    tile: bool,
+    properties: Properties,
+}
+
+#[derive(Default)]
+struct Tile {
+    /// The `texture` property, or else the tile's image, resolved against
+    /// the directory of the file that defines the tile.
+    texture: Option<String>,
+    properties: Properties,
+}
+
+impl Tile {
+    fn new(image: Option<&str>, properties: Properties, dir: &Path) -> Self {
+        let texture = properties
+            .get("texture")
+            .map(String::as_str)
+            .or(image)
+            .map(|path| relative(dir, path));
+        Tile {
+            texture,
+            properties,
+        }
+    }
+}
+
+/// Object classes the importer places on the map.
+const OBJECT_KINDS: [&str; 5] = ["spawn", "goal", "door", "enemy", "pickup"];
+
+/// The top bits of a gid hold flip flags, which don't matter for walls.
+const GID_MASK: u32 = 0x0FFF_FFFF;
+
+fn error(message: impl Into<String>) -> MazeError {
+    MazeError::Tiled(message.into())
+}
+
+/// Whether `filename` is a Tiled map: a `.tmx` or `.tmj` file, or a
+/// `.json` file holding a Tiled map rather than one of our own. Every
+/// loader checks this first, so they all agree on which files are Tiled's.
+pub fn is_tiled(filename: &str) -> bool {
+    filename.ends_with(".tmx")
+        || filename.ends_with(".tmj")
+        || filename.ends_with(".json")
+            && fs::read_to_string(filename).is_ok_and(|text| is_tiled_json(&text))
+}
+
+fn is_tiled_json(text: &str) -> bool {
+    serde_json::from_str::<serde_json::Value>(text)
+        .is_ok_and(|value| value["type"] == "map" && value["layers"].is_array())
+}
+
+/// Imports a Tiled orthogonal map. The tile layer named `walls`, or else the
+/// first one not named `floor` or `ceiling`, gives the walls: each tile uses
+/// its `char` property, or a letter of its own when it has a `texture`
+/// property or image, or `+`. Objects are placed by their class, type or
+/// name: `spawn` (with an `angle` property in degrees), `goal`, `door`,
+/// `enemy` and `pickup`.
+pub fn load(filename: &str) -> Result<Maze, MazeError> {
+    let map = read(filename)?;
+    let (rows, extras) = convert(&map)?;
+    Maze::with_extras(rows, extras)
+}
+
+/// Render layers of a Tiled map: wall tiles with a `height` property in
+/// blocks, and tiles in layers named `floor` and `ceiling` with a `floor`
+/// or `ceiling` property naming the texture.
+pub fn load_layers(filename: &str) -> Result<Layers, MazeError> {
+    let map = read(filename)?;
+    let blank = vec![vec![' '; map.width]; map.height];
+    let mut layers = Layers {
+        floor: blank.clone(),
+        ceiling: blank.clone(),
+        height: blank,
+    };
+
+    let walls = wall_layer(&map);
+    for (n, layer) in map.tile_layers.iter().enumerate() {
+        let (target, property) = match layer.name.to_lowercase().as_str() {
+            "floor" => (&mut layers.floor, "floor"),
+            "ceiling" => (&mut layers.ceiling, "ceiling"),
+            _ if Some(n) == walls => (&mut layers.height, "height"),
+            _ => continue,
+        };
+        for (i, &gid) in layer.gids.iter().enumerate() {
+            let Some(value) = map
+                .tiles
+                .get(&gid)
+                .and_then(|tile| tile.properties.get(property))
+            else {
+                continue;
+            };
+            let cell = if property == "height" {
+                let quarters = value
+                    .parse::<f32>()
+                    .map_or(4.0, |blocks| (blocks * 4.0).round());
+                char::from_digit(quarters.clamp(1.0, 9.0) as u32, 10)
+            } else {
+                value.chars().next()
+            };
+            target[i / map.width][i % map.width] = cell.unwrap_or(' ');
+        }
+    }
+    Ok(layers)
+}
+
+/// Index of the tile layer holding the walls.
+fn wall_layer(map: &TiledMap) -> Option<usize> {
+    let layers = &map.tile_layers;
+    layers
+        .iter()
+        .position(|layer| layer.name.eq_ignore_ascii_case("walls"))
+        .or_else(|| {
+            layers.iter().position(|layer| {
+                !layer.name.eq_ignore_ascii_case("floor")
+                    && !layer.name.eq_ignore_ascii_case("ceiling")
+            })
+        })
+}
+
+fn convert(map: &TiledMap) -> Result<(Vec<Vec<char>>, MapExtras), MazeError> {
+    let walls = wall_layer(map).ok_or_else(|| error("map has no wall tile layer"))?;
+    let walls = &map.tile_layers[walls];
+    let mut rows = vec![vec![' '; map.width]; map.height];
+    let mut extras = MapExtras::default();
+
+    // Tiles that bring their own texture need a char of their own, since
+    // the renderer looks textures up by char.
+    let mut letters = ('A'..='Z').filter(|&c| !TILES.contains(&c));
+    let mut chars: HashMap<u32, char> = HashMap::new();
+    for (i, &gid) in walls.gids.iter().enumerate() {
+        if gid == 0 {
+            continue;
+        }
+        let cell = match chars.get(&gid) {
+            Some(&cell) => cell,
+            None => {
+                let tile = map.tiles.get(&gid);
+                let texture = tile.and_then(|tile| tile.texture.clone());
+                let cell = match tile.and_then(|tile| tile.properties.get("char")) {
+                    Some(value) => value.chars().next().unwrap_or('+'),
+                    None if texture.is_some() => letters
+                        .next()
+                        .ok_or_else(|| error("too many textured wall tiles"))?,
+                    None => '+',
+                };
+                if let Some(texture) = texture {
+                    extras.textures.insert(cell, texture);
+                }
+                chars.insert(gid, cell);
+                cell
+            }
+        };
+        rows[i / map.width][i % map.width] = cell;
+    }
+
+    for object in &map.objects {
+        // Tile objects are anchored at their bottom-left corner, everything
+        // else at the top-left; place each by its middle.
+        let top = if object.tile {
+            object.y - object.height
+        } else {
+            object.y
+        };
+        let x = ((object.x + object.width / 2.0) / map.tile_width).floor();
+        let y = ((top + object.height / 2.0) / map.tile_height).floor();
+        let kind = object.kind.to_lowercase();
+        // Objects of any other kind are ignored, wherever they are.
+        if !OBJECT_KINDS.contains(&kind.as_str()) {
+            continue;
+        }
+        if x < 0.0 || y < 0.0 || x as usize >= map.width || y as usize >= map.height {
+            return Err(error(format!(
+                "{} object lies outside the map",
+                object.kind
+            )));
+        }
+        let cell = (x as usize, y as usize);
+
+        match kind.as_str() {
+            "spawn" => {
+                let degrees = object
+                    .properties
+                    .get("angle")
+                    .and_then(|angle| angle.parse::<f32>().ok())
+                    .unwrap_or(0.0);
+                extras.spawn = Some(Spawn {
+                    cell,
+                    angle: degrees.to_radians(),
+                });
+            }
+            "goal" => rows[cell.1][cell.0] = 'g',
+            "door" => rows[cell.1][cell.0] = 'D',
+            "enemy" => extras.enemies.push(cell),
+            "pickup" => extras.pickups.push(cell),
+            _ => {}
+        }
+    }
+    Ok((rows, extras))
+}
+
+fn read(filename: &str) -> Result<TiledMap, MazeError> {
+    let text = fs::read_to_string(filename).map_err(|err| match err.kind() {
+        std::io::ErrorKind::NotFound => MazeError::MissingFile(filename.to_string()),
+        _ => MazeError::Io(err),
+    })?;
+    let dir = Path::new(filename).parent().unwrap_or(Path::new(""));
+    if filename.ends_with(".tmx") {
+        read_tmx(&text, dir)
+    } else {
+        read_json(&text, dir)
+    }
+}
+
+fn check_header(orientation: &str, infinite: bool) -> Result<(), MazeError> {
+    if orientation != "orthogonal" {
+        return Err(error(format!("{} maps are not supported", orientation)));
+    }
+    if infinite {
+        return Err(error("infinite maps are not supported"));
+    }
+    Ok(())
+}
+
+fn parse_csv(data: &str) -> Result<Vec<u32>, MazeError> {
+    data.split(',')
+        .map(str::trim)
+        .filter(|gid| !gid.is_empty())
+        .map(|gid| {
+            gid.parse::<u32>()
+                .map(|gid| gid & GID_MASK)
+                .map_err(|_| error(format!("bad tile id {:?}", gid)))
+        })
+        .collect()
+}
+
+fn read_tmx(text: &str, dir: &Path) -> Result<TiledMap, MazeError> {
+    let document = roxmltree::Document::parse(text).map_err(|err| error(err.to_string()))?;
+    let root = document.root_element();
+    let number = |node: roxmltree::Node, name: &str| -> Result<f32, MazeError> {
+        attribute(node, name).parse().map_err(|_| {
+            error(format!(
+                "<{}> needs a numeric {}",
+                node.tag_name().name(),
+                name
+            ))
+        })
+    };
+    check_header(
+        root.attribute("orientation").unwrap_or("orthogonal"),
+        attribute(root, "infinite") == "1",
+    )?;
+
+    let mut map = TiledMap {
+        width: number(root, "width")? as usize,
+        height: number(root, "height")? as usize,
+        tile_width: number(root, "tilewidth")?,
+        tile_height: number(root, "tileheight")?,
+        tile_layers: Vec::new(),
+        objects: Vec::new(),
+        tiles: HashMap::new(),
+    };
+
+    for node in root
+        .descendants()
+        .filter(|node| node.has_tag_name("tileset"))
+    {
+        let first_gid = number(node, "firstgid")? as u32;
+        match node.attribute("source") {
+            Some(source) => {
+                let path = dir.join(source);
+                let text = fs::read_to_string(&path).map_err(MazeError::Io)?;
+                let tileset = roxmltree::Document::parse(&text)
+                    .map_err(|err| error(format!("{}: {}", path.display(), err)))?;
+                let tileset_dir = path.parent().unwrap_or(dir);
+                read_tmx_tiles(
+                    tileset.root_element(),
+                    first_gid,
+                    tileset_dir,
+                    &mut map.tiles,
+                );
+            }
+            None => read_tmx_tiles(node, first_gid, dir, &mut map.tiles),
+        }
+    }
+
+    for node in root.descendants().filter(|node| node.has_tag_name("layer")) {
+        let data = node
+            .children()
+            .find(|child| child.has_tag_name("data"))
+            .ok_or_else(|| error("tile layer without data"))?;
+        if data.attribute("compression").is_some()
+            || data.children().any(|child| child.has_tag_name("chunk"))
+        {
+            return Err(error("compressed or chunked layer data is not supported"));
+        }
+        let gids = match data.attribute("encoding") {
+            Some("csv") => parse_csv(data.text().unwrap_or(""))?,
+            None => data
+                .children()
+                .filter(|child| child.has_tag_name("tile"))
+                .map(|tile| attribute(tile, "gid").parse::<u32>().unwrap_or(0) & GID_MASK)
+                .collect(),
+            Some(encoding) => {
+                return Err(error(format!(
+                    "{} layer data is not supported, save the map with CSV",
+                    encoding
+                )))
+            }
+        };
+        if gids.len() != map.width * map.height {
+            return Err(error(format!(
+                "layer {:?} has the wrong size",
+                attribute(node, "name")
+            )));
+        }
+        map.tile_layers.push(TileLayer {
+            name: attribute(node, "name").to_string(),
+            gids,
+        });
+    }
+
+    for node in root
+        .descendants()
+        .filter(|node| node.has_tag_name("object"))
+    {
+        let kind = ["class", "type", "name"]
+            .iter()
+            .map(|name| attribute(node, name))
+            .find(|kind| !kind.is_empty())
+            .unwrap_or("");
+        map.objects.push(Object {
+            kind: kind.to_string(),
+            x: number(node, "x")?,
+            y: number(node, "y")?,
+            width: attribute(node, "width").parse().unwrap_or(0.0),
+            height: attribute(node, "height").parse().unwrap_or(0.0),
+            tile: node.attribute("gid").is_some(),
+            properties: tmx_properties(node),
+        });
+    }
+    Ok(map)
+}
+
+fn attribute<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> &'a str {
+    node.attribute(name).unwrap_or("")
+}
+
+fn read_tmx_tiles(
+    tileset: roxmltree::Node,
+    first_gid: u32,
+    dir: &Path,
+    tiles: &mut HashMap<u32, Tile>,
+) {
+    for node in tileset.children().filter(|node| node.has_tag_name("tile")) {
+        let Some(id) = node.attribute("id").and_then(|id| id.parse::<u32>().ok()) else {
+            continue;
+        };
+        let image = node
+            .children()
+            .find(|child| child.has_tag_name("image"))
+            .and_then(|image| image.attribute("source"));
+        tiles.insert(first_gid + id, Tile::new(image, tmx_properties(node), dir));
+    }
+}
+
+fn tmx_properties(node: roxmltree::Node) -> Properties {
+    node.children()
+        .filter(|child| child.has_tag_name("properties"))
+        .flat_map(|properties| properties.children())
+        .filter(|property| property.has_tag_name("property"))
+        .filter_map(|property| {
+            let value = property.attribute("value").or(property.text())?;
+            Some((property.attribute("name")?.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+fn read_json(text: &str, dir: &Path) -> Result<TiledMap, MazeError> {
+    use serde_json::Value;
+
+    let root: Value = serde_json::from_str(text).map_err(MazeError::Json)?;
+    let number = |value: &Value, name: &str| -> Result<f32, MazeError> {
+        value[name]
+            .as_f64()
+            .map(|n| n as f32)
+            .ok_or_else(|| error(format!("map needs a numeric {}", name)))
+    };
+    check_header(
+        root["orientation"].as_str().unwrap_or("orthogonal"),
+        root["infinite"].as_bool().unwrap_or(false),
+    )?;
+
+    let mut map = TiledMap {
+        width: number(&root, "width")? as usize,
+        height: number(&root, "height")? as usize,
+        tile_width: number(&root, "tilewidth")?,
+        tile_height: number(&root, "tileheight")?,
+        tile_layers: Vec::new(),
+        objects: Vec::new(),
+        tiles: HashMap::new(),
+    };
+
+    for tileset in root["tilesets"].as_array().into_iter().flatten() {
+        let first_gid = number(tileset, "firstgid")? as u32;
+        match tileset["source"].as_str() {
+            Some(source) => {
+                let path = dir.join(source);
+                let text = fs::read_to_string(&path).map_err(MazeError::Io)?;
+                let external: Value = serde_json::from_str(&text).map_err(MazeError::Json)?;
+                let tileset_dir = path.parent().unwrap_or(dir);
+                read_json_tiles(&external, first_gid, tileset_dir, &mut map.tiles);
+            }
+            None => read_json_tiles(tileset, first_gid, dir, &mut map.tiles),
+        }
+    }
+
+    // Group layers nest further layers; walk them all in order.
+    let mut pending: Vec<&Value> = root["layers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .rev()
+        .collect();
+    while let Some(layer) = pending.pop() {
+        match layer["type"].as_str() {
+            Some("group") => pending.extend(layer["layers"].as_array().into_iter().flatten().rev()),
+            Some("tilelayer") => {
+                let gids =
+                    match &layer["data"] {
+                        Value::Array(data) => data
+                            .iter()
+                            .map(|gid| gid.as_u64().unwrap_or(0) as u32 & GID_MASK)
+                            .collect(),
+                        Value::String(data) if layer["encoding"] == "csv" => parse_csv(data)?,
+                        _ => return Err(error(
+                            "base64 or chunked layer data is not supported, save the map with CSV",
+                        )),
+                    };
+                let name = layer["name"].as_str().unwrap_or("").to_string();
+                if gids.len() != map.width * map.height {
+                    return Err(error(format!("layer {:?} has the wrong size", name)));
+                }
+                map.tile_layers.push(TileLayer { name, gids });
+            }
+            Some("objectgroup") => {
+                for object in layer["objects"].as_array().into_iter().flatten() {
+                    let kind = ["class", "type", "name"]
+                        .iter()
+                        .filter_map(|name| object[name].as_str())
+                        .find(|kind| !kind.is_empty())
+                        .unwrap_or("");
+                    map.objects.push(Object {
+                        kind: kind.to_string(),
+                        x: number(object, "x")?,
+                        y: number(object, "y")?,
+                        width: object["width"].as_f64().unwrap_or(0.0) as f32,
+                        height: object["height"].as_f64().unwrap_or(0.0) as f32,
+                        tile: object["gid"].is_u64(),
+                        properties: json_properties(object),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(map)
+}
+
+fn read_json_tiles(
+    tileset: &serde_json::Value,
+    first_gid: u32,
+    dir: &Path,
+    tiles: &mut HashMap<u32, Tile>,
+) {
+    for tile in tileset["tiles"].as_array().into_iter().flatten() {
+        let Some(id) = tile["id"].as_u64() else {
+            continue;
+        };
+        tiles.insert(
+            first_gid + id as u32,
+            Tile::new(tile["image"].as_str(), json_properties(tile), dir),
+        );
+    }
+}
+
+fn json_properties(value: &serde_json::Value) -> Properties {
+    value["properties"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|property| {
+            let name = property["name"].as_str()?.to_string();
+            let value = match &property["value"] {
+                serde_json::Value::String(value) => value.clone(),
+                value => value.to_string(),
+            };
+            Some((name, value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: &str = "tests/fixtures/tiled";
+
+    /// `room.tmx` and `room.tmj` hold the same map, with the textured wall
+    /// given by a `texture` property in one and a tile image in the other.
+    fn check_room(filename: &str) {
+        let maze = load(filename).unwrap();
+        let rows: Vec<String> = maze.rows().iter().map(|row| row.iter().collect()).collect();
+        assert_eq!(rows, ["++++++", "+    +", "+ A  g", "+    +", "++++++"]);
+        assert_eq!(maze.spawn().cell, (1, 1));
+        assert!((maze.spawn().angle - 90f32.to_radians()).abs() < 1e-6);
+        assert_eq!(maze.enemies(), [(4, 3)]);
+        assert_eq!(maze.pickups(), [(1, 3)]);
+        let texture = Path::new(FIXTURES).join("wall.png");
+        assert_eq!(maze.textures()[&'A'], texture.to_str().unwrap());
+
+        let layers = load_layers(filename).unwrap();
+        assert_eq!(layers.wall_height(2, 2), 2.0);
+        assert_eq!(layers.wall_height(0, 0), 1.0);
+        assert!(layers.floor.iter().flatten().all(|&cell| cell == '2'));
+    }
+
+    #[test]
+    fn imports_tmx() {
+        check_room(&format!("{}/room.tmx", FIXTURES));
+    }
+
+    #[test]
+    fn imports_tmj() {
+        check_room(&format!("{}/room.tmj", FIXTURES));
+    }
+
+    #[test]
+    fn detects_tiled_json_by_content() {
+        let dir = std::env::temp_dir().join(format!("raycaster-tiled-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let filename = dir.join("room.json");
+        fs::copy(format!("{}/room.tmj", FIXTURES), &filename).unwrap();
+        let filename = filename.to_str().unwrap();
+        assert!(is_tiled(filename));
+        assert_eq!(Layers::load(filename).wall_height(2, 2), 2.0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use super::Maze;
+use crate::path::{self, Diagonal, Path};
+
+/// Something wrong with a maze that loads but would still break in play.
+/// Unknown tiles, a missing or unreachable goal and a spawn inside a wall
+/// are already refused by the loader as a `MazeError`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// An open area that runs off the edge of the map, where rays leave the
+    /// grid. Listed once, with the first of its cells on the edge as the
+    /// place to add a wall.
+    UnboundedRegion { x: usize, y: usize, cells: usize },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::UnboundedRegion { x, y, cells } => write!(
+                f,
+                "open region of {} cell{} is not enclosed by walls: missing border wall at column {}, row {}",
+                cells,
+                if *cells == 1 { "" } else { "s" },
+                x,
+                y
+            ),
+        }
+    }
+}
+
+/// Checks a loaded maze for the problems the loader lets through. Maps that
+/// set a view distance are meant to be open, so their borders are not
+/// checked.
+pub fn check(maze: &Maze) -> Vec<Problem> {
+    if maze.view_distance().is_some() {
+        return Vec::new();
+    }
+    let (width, height) = (maze.width(), maze.height());
+    let open = |x: usize, y: usize| path::is_passable(maze, None, x, y);
+    let on_edge = |x: usize, y: usize| x == 0 || y == 0 || x + 1 == width || y + 1 == height;
+
+    // Flood each open area once and flag the ones that touch the edge.
+    let mut problems = Vec::new();
+    let mut seen = vec![vec![false; width]; height];
+    for y in 0..height {
+        for x in 0..width {
+            if seen[y][x] || !open(x, y) {
+                continue;
+            }
+            seen[y][x] = true;
+            let mut queue = VecDeque::from([(x, y)]);
+            let mut cells = 0;
+            let mut hole = None;
+            while let Some((cx, cy)) = queue.pop_front() {
+                cells += 1;
+                // Keep the edge cell that comes first in reading order.
+                if on_edge(cx, cy) && hole.is_none_or(|first| (cy, cx) < first) {
+                    hole = Some((cy, cx));
+                }
+                let neighbors = [
+                    (cx.wrapping_sub(1), cy),
+                    (cx + 1, cy),
+                    (cx, cy.wrapping_sub(1)),
+                    (cx, cy + 1),
+                ];
+                for (nx, ny) in neighbors {
+                    if nx < width && ny < height && !seen[ny][nx] && open(nx, ny) {
+                        seen[ny][nx] = true;
+                        queue.push_back((nx, ny));
+                    }
+                }
+            }
+            if let Some((y, x)) = hole {
+                problems.push(Problem::UnboundedRegion { x, y, cells });
+            }
+        }
+    }
+    problems
+}
+
+/// The shortest walk from the spawn into a goal tile, with every door open.
+pub fn solution(maze: &Maze) -> Option<Path> {
+    (0..maze.height())
+        .flat_map(|y| (0..maze.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| maze.is_goal(x, y))
+        .filter_map(|goal| path::find_path(maze, None, maze.spawn().cell, goal, Diagonal::Never))
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
@@ -0,0 +1,86 @@
+use crate::framebuffer::Framebuffer;
+use crate::hud::draw_text;
+use crate::input::{Action, Binding, Input};
+use gilrs::Button;
+use minifb::Key;
+
+/// Characters that fit across the screen at the HUD font size.
+const LINE_LENGTH: usize = 41;
+
+/// The pause screen, listing every action with its bindings. It is driven
+/// by fixed keys rather than the bindings, so a bad binding can always be
+/// undone: Up and Down (or the D-pad) pick an action, Enter (or South)
+/// waits for the next input to bind to it, and Escape (or East) backs out.
+#[derive(Default)]
+pub struct ControlsMenu {
+    selected: usize,
+    waiting: bool,
+}
+
+impl ControlsMenu {
+    /// Handles this frame's input and returns whether the menu stays open.
+    /// New bindings are written to `config_file` straight away.
+    pub fn update(&mut self, input: &mut Input, config_file: &str) -> bool {
+        let Some(fresh) = input.fresh() else {
+            return true;
+        };
+        let is = |key: Key, button: Button| {
+            fresh == Binding::Key(key) || fresh == Binding::Button(button)
+        };
+
+        if self.waiting {
+            self.waiting = false;
+            if !is(Key::Escape, Button::East) {
+                input
+                    .config
+                    .bindings
+                    .rebind(Action::ALL[self.selected], fresh);
+                if let Err(err) = input.config.save(config_file) {
+                    eprintln!("failed to save controls to {}: {}", config_file, err);
+                }
+            }
+            return true;
+        }
+
+        let count = Action::ALL.len();
+        if is(Key::Up, Button::DPadUp) {
+            self.selected = (self.selected + count - 1) % count;
+        } else if is(Key::Down, Button::DPadDown) {
+            self.selected = (self.selected + 1) % count;
+        } else if is(Key::Enter, Button::South) {
+            self.waiting = true;
+        } else if is(Key::Escape, Button::East) || input.pressed(Action::Pause) {
+            return false;
+        }
+        true
+    }
+
+    pub fn draw(&self, framebuffer: &mut Framebuffer, input: &Input) {
+        draw_text(framebuffer, "PAUSED - CONTROLS", 40, 40);
+        for (row, action) in Action::ALL.into_iter().enumerate() {
+            let marker = if row == self.selected { '>' } else { ' ' };
+            let mut line = format!("{}{:<13}", marker, action.label());
+            let bindings = input.config.bindings.get(action);
+            // List whole bindings while they fit and count the rest.
+            for (shown, binding) in bindings.iter().enumerate() {
+                let separator = if shown == 0 { "" } else { ", " };
+                let more = bindings.len() - shown;
+                let entry = format!("{}{}", separator, binding);
+                let room = LINE_LENGTH - line.len() - if more > 1 { 3 } else { 0 };
+                if entry.len() > room {
+                    line += &format!(" +{}", more);
+                    break;
+                }
+                line += &entry;
+            }
+            draw_text(framebuffer, &line, 40, 120 + row * 50);
+        }
+
+        let hint = if self.waiting {
+            format!("PRESS AN INPUT FOR {}", Action::ALL[self.selected].label())
+        } else {
+            "ENTER: REBIND  ESC: RESUME".to_string()
+        };
+        draw_text(framebuffer, &hint, 40, 760);
+    }
+}
@@ -0,0 +1,221 @@
+use crate::door::Doors;
+use crate::maze::Maze;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::f32::consts::SQRT_2;
+
+pub type Cell = (usize, usize);
+
+/// Whether a path may step diagonally between cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Diagonal {
+    Never,
+    /// Only when both cells beside the step are open, so the path never
+    /// cuts across the corner of a wall.
+    IfClear,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    /// Every cell from the start to the destination, both included.
+    pub cells: Vec<Cell>,
+    /// Length in cells, with diagonal steps counting as `SQRT_2`.
+    pub distance: f32,
+}
+
+/// A cell on the open list, ordered so the heap pops the lowest estimate.
+struct Open {
+    estimate: f32,
+    cell: Cell,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+/// Whether a path can pass through `(x, y)`. With `doors` the current door
+/// state decides, as `Maze::is_blocked` does for the player; without it
+/// every door counts as open, since it can be opened on the way.
+pub fn is_passable(maze: &Maze, doors: Option<&Doors>, x: usize, y: usize) -> bool {
+    match doors {
+        Some(doors) => !maze.is_blocked(doors, x, y),
+        None => matches!(maze.cell(x, y), Some(' ') | Some('D')),
+    }
+}
+
+/// Finds the shortest path from `from` to `to` with A*. The destination may
+/// itself be a wall, such as the goal, in which case the path ends by
+/// stepping straight into it. Returns `None` when no path exists.
+pub fn find_path(
+    maze: &Maze,
+    doors: Option<&Doors>,
+    from: Cell,
+    to: Cell,
+    diagonal: Diagonal,
+) -> Option<Path> {
+    let (width, height) = (maze.width(), maze.height());
+    if from.0 >= width || from.1 >= height || to.0 >= width || to.1 >= height {
+        return None;
+    }
+    let index = |(x, y): Cell| y * width + x;
+    let heuristic = |(x, y): Cell| {
+        let dx = x.abs_diff(to.0) as f32;
+        let dy = y.abs_diff(to.1) as f32;
+        match diagonal {
+            Diagonal::Never => dx + dy,
+            Diagonal::IfClear => dx.max(dy) + (SQRT_2 - 1.0) * dx.min(dy),
+        }
+    };
+    let passable = |cell: Cell| is_passable(maze, doors, cell.0, cell.1);
+
+    let mut cost = vec![f32::INFINITY; width * height];
+    let mut came_from: Vec<Option<Cell>> = vec![None; width * height];
+    let mut open = BinaryHeap::new();
+    cost[index(from)] = 0.0;
+    open.push(Open {
+        estimate: heuristic(from),
+        cell: from,
+    });
+
+    while let Some(Open { estimate, cell }) = open.pop() {
+        if cell == to {
+            let mut cells = vec![to];
+            while let Some(previous) = came_from[index(cells[cells.len() - 1])] {
+                cells.push(previous);
+            }
+            cells.reverse();
+            return Some(Path {
+                cells,
+                distance: cost[index(to)],
+            });
+        }
+        // Skip stale entries left behind when a cheaper route was found.
+        if estimate > cost[index(cell)] + heuristic(cell) {
+            continue;
+        }
+
+        for (next, step) in neighbors(cell, width, height, diagonal) {
+            let diagonal_step = next.0 != cell.0 && next.1 != cell.1;
+            if next == to {
+                if diagonal_step && !passable(to) {
+                    continue;
+                }
+            } else if !passable(next) {
+                continue;
+            }
+            if diagonal_step && !(passable((next.0, cell.1)) && passable((cell.0, next.1))) {
+                continue;
+            }
+            let next_cost = cost[index(cell)] + step;
+            if next_cost < cost[index(next)] {
+                cost[index(next)] = next_cost;
+                came_from[index(next)] = Some(cell);
+                open.push(Open {
+                    estimate: next_cost + heuristic(next),
+                    cell: next,
+                });
+            }
+        }
+    }
+    None
+}
+
+/// The cells around `(x, y)` inside the grid, with the cost of stepping
+/// to each.
+fn neighbors(
+    (x, y): Cell,
+    width: usize,
+    height: usize,
+    diagonal: Diagonal,
+) -> impl Iterator<Item = (Cell, f32)> {
+    const STEPS: [(isize, isize); 8] = [
+        (1, 0),
+        (-1, 0),
+        (0, 1),
+        (0, -1),
+        (1, 1),
+        (1, -1),
+        (-1, 1),
+        (-1, -1),
+    ];
+    let count = match diagonal {
+        Diagonal::Never => 4,
+        Diagonal::IfClear => 8,
+    };
+    STEPS[..count].iter().filter_map(move |&(dx, dy)| {
+        let nx = x.checked_add_signed(dx).filter(|&nx| nx < width)?;
+        let ny = y.checked_add_signed(dy).filter(|&ny| ny < height)?;
+        let step = if dx != 0 && dy != 0 { SQRT_2 } else { 1.0 };
+        Some(((nx, ny), step))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn maze(rows: &[&str]) -> Maze {
+        Maze::from_rows(rows.iter().map(|row| row.chars().collect()).collect()).unwrap()
+    }
+
+    #[test]
+    fn finds_straight_path() {
+        let maze = maze(&["+-----+", "|p   g|", "+-----+"]);
+        let path = find_path(&maze, None, (1, 1), (4, 1), Diagonal::Never).unwrap();
+        assert_eq!(path.cells, [(1, 1), (2, 1), (3, 1), (4, 1)]);
+        assert_eq!(path.distance, 3.0);
+    }
+
+    #[test]
+    fn no_path_into_walled_off_area() {
+        let maze = maze(&["+-----+", "|p  g |", "+-----+", "|     |", "+-----+"]);
+        assert_eq!(
+            find_path(&maze, None, (1, 1), (2, 3), Diagonal::IfClear),
+            None
+        );
+    }
+
+    #[test]
+    fn path_ends_inside_goal_wall() {
+        let maze = maze(&["+-----+", "|p   g|", "+-----+"]);
+        let path = find_path(&maze, None, (1, 1), (5, 1), Diagonal::Never).unwrap();
+        assert_eq!(path.cells.last(), Some(&(5, 1)));
+        assert_eq!(path.distance, 4.0);
+        // Other walls can't be walked into, even next to the goal.
+        assert_eq!(
+            find_path(&maze, None, (1, 1), (6, 1), Diagonal::Never),
+            None
+        );
+    }
+
+    #[test]
+    fn diagonals_only_when_clear() {
+        let maze = maze(&["+-----+", "|p    |", "| +   |", "|     g", "+-----+"]);
+        let never = find_path(&maze, None, (3, 1), (5, 3), Diagonal::Never).unwrap();
+        let diagonal = find_path(&maze, None, (3, 1), (5, 3), Diagonal::IfClear).unwrap();
+        assert_eq!(never.distance, 4.0);
+        assert!((diagonal.distance - 2.0 * SQRT_2).abs() < 1e-5);
+        assert_eq!(diagonal.cells, [(3, 1), (4, 2), (5, 3)]);
+
+        // Around the pillar at (2, 2) no diagonal step is clear, so cutting
+        // its corners (1 + SQRT_2 + 1) is not allowed.
+        let around = find_path(&maze, None, (1, 1), (3, 3), Diagonal::IfClear).unwrap();
+        assert_eq!(around.distance, 4.0);
+    }
+}
@@ -1,228 +1,162 @@
-use crate::{sfx, trigger_win_condition};
-use gilrs::{Axis, Button, EventType, Gilrs};
-use minifb::{Key, MouseMode, Window, WindowOptions};
-use nalgebra_glm::{length, rotate_vec2, Vec2};
+use crate::door::Doors;
+use crate::input::{Action, Input};
+use crate::sfx;
+use nalgebra_glm::Vec2;
 use std::f32::consts::PI;
 
-use crate::maze::{is_blocked, is_goal};
+use crate::maze::Maze;
 
 pub struct Player {
     pub pos: Vec2,
     pub angle: f32,
     pub fov: f32,
-    pub last_mouse_x: f32,
+    /// How far the view is tilted up, in radians, or down when negative.
+    /// The renderer shows it by moving the horizon rather than tilting the
+    /// camera, so it stays within `MAX_PITCH`.
+    pub pitch: f32,
 }
 
 static mut TOTAL_DISTANCE: f32 = 0.0;
 const DISTANCE_THRESHOLD: f32 = 30.0;
 
+// Speeds are per second so movement is the same at any frame rate.
+const MOVE_SPEED: f32 = 300.0;
+const TURN_SPEED: f32 = PI * 2.4;
+/// Radians per pixel of mouse movement at a sensitivity of 1.0.
+const MOUSE_TURN: f32 = 0.003;
+/// Farthest the view tilts up or down. Past this the horizon shift looks
+/// more like a stretched picture than looking up.
+pub const MAX_PITCH: f32 = 0.6;
+/// How far the player keeps from walls, as a fraction of a block.
+const RADIUS: f32 = 0.2;
+
+/// Movement held through the bound keys and sticks, applied by `update` for
+/// as long as the simulation runs until the next frame's input.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Controls {
+    /// World units per second along the view direction, negative backwards.
+    pub forward: f32,
+    /// World units per second sideways, positive to the right.
+    pub strafe: f32,
+    /// Radians per second, positive to the right.
+    pub turn_rate: f32,
+}
+
+/// Scales a direction down to unit length, so pressing two directions at
+/// once is no faster than one. Shorter stick deflections are kept as is.
+fn clamp_length(direction: Vec2) -> Vec2 {
+    let length = direction.norm();
+    if length > 1.0 {
+        direction / length
+    } else {
+        direction
+    }
+}
+
+/// Reads this frame's input. Mouse look and door use take effect at once,
+/// while held movement is returned for `update` to integrate over time.
 pub fn process_events(
-    window: &Window,
+    input: &Input,
     player: &mut Player,
-    gilrs: &mut Gilrs,
-    maze: &Vec<Vec<char>>,
+    doors: &mut Doors,
     block_size: usize,
     stream_handle: &rodio::OutputStreamHandle,
-) {
-    let forward = Vec2::new(player.angle.cos(), player.angle.sin());
-    let mut moved = false;
-    let old_pos = player.pos.clone();
-
-    const MOVE_SPEED_KEYBOARD: f32 = 5.0;
-    const ROTATION_SPEED_KEYBOARD: f32 = PI / 25.0;
-    const DEAD_ZONE: f32 = 0.5;
-    const MOVE_SPEED_CONTROLLER: f32 = 3.0;
-    const ROTATION_SPEED_CONTROLLER: f32 = PI / 50.0;
-    const ROTATION_SPEED_MOUSE: f32 = PI / 75.0;
-
-    let forward = Vec2::new(player.angle.cos(), player.angle.sin());
-
-    if window.is_key_down(Key::A) {
-        player.angle -= ROTATION_SPEED_KEYBOARD;
-    }
-
-    if window.is_key_down(Key::D) {
-        player.angle += ROTATION_SPEED_KEYBOARD;
-    }
-
-    if window.is_key_down(Key::W) {
-        let move_vec = forward * MOVE_SPEED_KEYBOARD;
-        let new_pos = player.pos + move_vec;
-        if !is_blocked(
-            maze,
-            new_pos.x as usize / block_size,
-            new_pos.y as usize / block_size,
-        ) {
-            player.pos = new_pos;
-            moved = true;
-        }
-        if is_goal(
-            maze,
-            new_pos.x as usize / block_size,
-            new_pos.y as usize / block_size,
-        ) {
-            trigger_win_condition();
-        }
-    }
-
-    if window.is_key_down(Key::S) {
-        let move_vec = forward * -MOVE_SPEED_KEYBOARD;
-        let new_pos = player.pos + move_vec;
-        if !is_blocked(
-            maze,
-            new_pos.x as usize / block_size,
-            new_pos.y as usize / block_size,
-        ) {
-            player.pos = new_pos;
-            moved = true;
-        }
-        if is_goal(
-            maze,
-            new_pos.x as usize / block_size,
-            new_pos.y as usize / block_size,
-        ) {
-            trigger_win_condition();
-        }
-    }
-
-    if window.is_key_down(Key::Left) {
-        player.angle -= ROTATION_SPEED_KEYBOARD;
+) -> Controls {
+    if input.pressed(Action::Use) && doors.use_door(player.pos, player.angle, block_size) {
+        let _ = sfx::play_door_sound(stream_handle);
     }
 
-    if window.is_key_down(Key::Right) {
-        player.angle += ROTATION_SPEED_KEYBOARD;
+    let mouse = &input.config.mouse;
+    let (delta_x, delta_y) = input.mouse_motion();
+    let delta_y = if mouse.invert_y { -delta_y } else { delta_y };
+    player.angle += delta_x * MOUSE_TURN * mouse.sensitivity;
+    player.pitch =
+        (player.pitch - delta_y * MOUSE_TURN * mouse.sensitivity).clamp(-MAX_PITCH, MAX_PITCH);
+
+    // Forward in x and rightward in y.
+    let movement = clamp_length(Vec2::new(
+        input.axis(Action::MoveForward, Action::MoveBackward),
+        input.axis(Action::StrafeRight, Action::StrafeLeft),
+    )) * MOVE_SPEED;
+    Controls {
+        forward: movement.x,
+        strafe: movement.y,
+        turn_rate: input.axis(Action::TurnRight, Action::TurnLeft) * TURN_SPEED,
     }
+}
 
-    if window.is_key_down(Key::Up) {
-        let move_vec = forward * MOVE_SPEED_KEYBOARD;
-        let new_pos = player.pos + move_vec;
-        if !is_blocked(
-            maze,
-            new_pos.x as usize / block_size,
-            new_pos.y as usize / block_size,
-        ) {
-            player.pos = new_pos;
-            moved = true;
-        }
-        if is_goal(
-            maze,
-            new_pos.x as usize / block_size,
-            new_pos.y as usize / block_size,
-        ) {
-            trigger_win_condition();
-        }
+/// Advances the player by `dt` seconds of `controls`. Returns whether the
+/// player walked into the goal.
+pub fn update(
+    player: &mut Player,
+    controls: &Controls,
+    maze: &Maze,
+    doors: &Doors,
+    block_size: usize,
+    dt: f32,
+    stream_handle: &rodio::OutputStreamHandle,
+) -> bool {
+    player.angle += controls.turn_rate * dt;
+    if controls.forward == 0.0 && controls.strafe == 0.0 {
+        return false;
     }
 
-    if window.is_key_down(Key::Down) {
-        let move_vec = forward * -MOVE_SPEED_KEYBOARD;
-        let new_pos = player.pos + move_vec;
-        if !is_blocked(
-            maze,
-            new_pos.x as usize / block_size,
-            new_pos.y as usize / block_size,
-        ) {
-            player.pos = new_pos;
-            moved = true;
-        }
-        if is_goal(
-            maze,
-            new_pos.x as usize / block_size,
-            new_pos.y as usize / block_size,
-        ) {
-            trigger_win_condition();
+    let old_pos = player.pos;
+    let forward = Vec2::new(player.angle.cos(), player.angle.sin());
+    let right = Vec2::new(-forward.y, forward.x);
+    let step = (forward * controls.forward + right * controls.strafe) * dt;
+    let radius = RADIUS * block_size as f32;
+    let goal = |x: usize, y: usize| maze.is_goal(x, y);
+    let won = overlap(player.pos + step, radius, block_size, goal) > 0.0;
+
+    // Moving along each axis on its own lets the player slide along a wall
+    // instead of stopping dead when grazing it. A step is also taken when it
+    // digs less deep, so a door closing on the player's edge can't trap them.
+    let blocked = |x: usize, y: usize| maze.is_blocked(doors, x, y);
+    for axis_step in [Vec2::new(step.x, 0.0), Vec2::new(0.0, step.y)] {
+        let next = player.pos + axis_step;
+        let depth = overlap(next, radius, block_size, blocked);
+        if depth == 0.0 || depth < overlap(player.pos, radius, block_size, blocked) {
+            player.pos = next;
         }
     }
-
-    if let Some((mouse_x, _)) = window.get_mouse_pos(MouseMode::Pass) {
-        let delta_x = mouse_x as f32 - player.last_mouse_x;
-        player.angle += delta_x * ROTATION_SPEED_MOUSE;
-        player.last_mouse_x = mouse_x as f32;
+    if player.pos == old_pos {
+        return won;
     }
 
-    while let Some(event) = gilrs.next_event() {
-        match event.event {
-            EventType::ButtonPressed(button, _) => match button {
-                Button::DPadLeft => player.angle -= ROTATION_SPEED_CONTROLLER,
-                Button::DPadRight => player.angle += ROTATION_SPEED_CONTROLLER,
-                Button::DPadUp => {
-                    let move_vec = forward * MOVE_SPEED_CONTROLLER;
-                    let new_pos = player.pos + move_vec;
-                    if !is_blocked(
-                        maze,
-                        new_pos.x as usize / block_size,
-                        new_pos.y as usize / block_size,
-                    ) {
-                        player.pos = new_pos;
-                        moved = true;
-                    }
-                    if is_goal(
-                        maze,
-                        new_pos.x as usize / block_size,
-                        new_pos.y as usize / block_size,
-                    ) {
-                        trigger_win_condition();
-                    }
-                }
-                Button::DPadDown => {
-                    let move_vec = forward * -MOVE_SPEED_CONTROLLER;
-                    let new_pos = player.pos + move_vec;
-                    if !is_blocked(
-                        maze,
-                        new_pos.x as usize / block_size,
-                        new_pos.y as usize / block_size,
-                    ) {
-                        player.pos = new_pos;
-                        moved = true;
-                    }
-                    if is_goal(
-                        maze,
-                        new_pos.x as usize / block_size,
-                        new_pos.y as usize / block_size,
-                    ) {
-                        trigger_win_condition();
-                    }
-                }
-                _ => {}
-            },
-            EventType::AxisChanged(Axis::LeftStickX, value, _) => {
-                if value > DEAD_ZONE || value < -DEAD_ZONE {
-                    player.angle += value * ROTATION_SPEED_CONTROLLER;
-                }
-            }
-            EventType::AxisChanged(Axis::LeftStickY, value, _) => {
-                if value > DEAD_ZONE || value < -DEAD_ZONE {
-                    let move_vec = forward * (value * MOVE_SPEED_CONTROLLER);
-                    let new_pos = player.pos + move_vec;
-                    if !is_blocked(
-                        maze,
-                        new_pos.x as usize / block_size,
-                        new_pos.y as usize / block_size,
-                    ) {
-                        player.pos = new_pos;
-                        moved = true;
-                    }
-                    if is_goal(
-                        maze,
-                        new_pos.x as usize / block_size,
-                        new_pos.y as usize / block_size,
-                    ) {
-                        trigger_win_condition();
-                    }
-                }
-            }
-            _ => {}
+    let distance = nalgebra_glm::distance(&old_pos, &player.pos);
+    unsafe {
+        TOTAL_DISTANCE += distance;
+        if TOTAL_DISTANCE >= DISTANCE_THRESHOLD && sfx::play_footstep_sound(stream_handle).is_ok() {
+            TOTAL_DISTANCE = 0.0;
         }
     }
+    won
+}
 
-    if moved {
-        let distance = nalgebra_glm::distance(&old_pos, &player.pos);
-        unsafe {
-            TOTAL_DISTANCE += distance;
-            if TOTAL_DISTANCE >= DISTANCE_THRESHOLD {
-                if let Ok(_) = sfx::play_footstep_sound(stream_handle) {
-                    TOTAL_DISTANCE = 0.0;
-                }
+/// How deep a circle at `pos` reaches into the cells `solid` picks out, or
+/// 0.0 when it touches none of them.
+fn overlap(pos: Vec2, radius: f32, block_size: usize, solid: impl Fn(usize, usize) -> bool) -> f32 {
+    let block = block_size as f32;
+    let first_x = ((pos.x - radius) / block).floor() as isize;
+    let last_x = ((pos.x + radius) / block).floor() as isize;
+    let first_y = ((pos.y - radius) / block).floor() as isize;
+    let last_y = ((pos.y + radius) / block).floor() as isize;
+
+    let mut depth: f32 = 0.0;
+    for y in first_y..=last_y {
+        for x in first_x..=last_x {
+            // Cells left of or above the map wrap to indices past its end,
+            // which `Maze::cell` treats as off the map too.
+            if !solid(x as usize, y as usize) {
+                continue;
             }
+            let nearest = Vec2::new(
+                pos.x.clamp(x as f32 * block, (x + 1) as f32 * block),
+                pos.y.clamp(y as f32 * block, (y + 1) as f32 * block),
+            );
+            depth = depth.max(radius - nalgebra_glm::distance(&pos, &nearest));
         }
     }
+    depth
 }
@@ -0,0 +1,579 @@
+use nalgebra_glm::Vec2;
+use once_cell::sync::OnceCell;
+use std::collections::{BTreeMap, HashMap};
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use crate::caster::{cast_ray, Face, Ray};
+use crate::door::Doors;
+use crate::fog::{shade, Fog, SIDE_SHADE};
+use crate::framebuffer::Framebuffer;
+use crate::maze::{layer_cell, Layers, Maze};
+use crate::player::Player;
+use crate::texture::Texture;
+
+/// The textures every map can use, loaded from `assets/`.
+struct Builtin {
+    wall1: Texture,
+    wall2: Texture,
+    wall3: Texture,
+    wall4: Texture,
+    floor1: Texture,
+    floor2: Texture,
+    floor3: Texture,
+    ceiling1: Texture,
+    ceiling2: Texture,
+    ceiling3: Texture,
+    door: Texture,
+    sky: Texture,
+    enemy: Texture,
+}
+
+/// Loaded by the first renderer and shared by every one after it.
+static BUILTIN: OnceCell<Arc<Builtin>> = OnceCell::new();
+
+impl Builtin {
+    fn load() -> image::ImageResult<Self> {
+        Ok(Builtin {
+            wall1: Texture::load("assets/asset7.png")?,
+            wall2: Texture::load("assets/asset2.png")?,
+            wall3: Texture::load("assets/asset10.png")?,
+            wall4: Texture::load("assets/asset13.png")?,
+            floor1: Texture::load("assets/asset1.png")?,
+            floor2: Texture::load("assets/asset12.png")?,
+            floor3: Texture::load("assets/asset5.png")?,
+            ceiling1: Texture::load("assets/asset3.png")?,
+            ceiling2: Texture::load("assets/asset12.png")?,
+            ceiling3: Texture::load("assets/asset8.png")?,
+            door: Texture::load("assets/asset8.png")?,
+            sky: Texture::load("assets/sky.jpg")?,
+            enemy: Texture::load("assets/moai.png")?,
+        })
+    }
+
+    fn wall_color(&self, cell: char, u: f32, v: f32) -> u32 {
+        match cell {
+            '+' => self.wall4.sample(u, v),
+            '-' => self.wall3.sample(u, v),
+            '|' => self.wall2.sample(u, v),
+            'g' => self.wall1.sample(u, v),
+            'D' => self.door.sample(u, v),
+            _ => 0x000000,
+        }
+    }
+
+    fn floor_color(&self, cell: char, u: f32, v: f32) -> u32 {
+        match cell {
+            '2' => self.floor2.sample(u, v),
+            '3' => self.floor3.sample(u, v),
+            _ => self.floor1.sample(u, v),
+        }
+    }
+
+    fn ceiling_color(&self, cell: char, u: f32, v: f32) -> Option<u32> {
+        match cell {
+            '1' => Some(self.ceiling1.sample(u, v)),
+            '2' => Some(self.ceiling2.sample(u, v)),
+            '3' => Some(self.ceiling3.sample(u, v)),
+            _ => None,
+        }
+    }
+}
+
+fn color_distance(color1: u32, color2: u32) -> u32 {
+    let r1 = (color1 >> 16) & 0xFF;
+    let g1 = (color1 >> 8) & 0xFF;
+    let b1 = color1 & 0xFF;
+
+    let r2 = (color2 >> 16) & 0xFF;
+    let g2 = (color2 >> 8) & 0xFF;
+    let b2 = color2 & 0xFF;
+
+    let r_diff = r1.abs_diff(r2);
+    let g_diff = g1.abs_diff(g2);
+    let b_diff = b1.abs_diff(b2);
+
+    r_diff + g_diff + b_diff
+}
+
+struct Scene<'a> {
+    player: &'a Player,
+    maze: &'a Maze,
+    doors: &'a Doors,
+    layers: &'a Layers,
+    /// Height of the tallest wall in the map, looked up once per frame.
+    max_height: f32,
+    fog: &'a Fog,
+    view_distance: f32,
+    /// Screen row of the horizon, moved off the middle by the player's pitch.
+    horizon: f32,
+    builtin: &'a Builtin,
+    wall_textures: &'a HashMap<char, Texture>,
+    block_size: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Scene<'_> {
+    /// Draws column `i` into `pixels` (one entry per row) and returns the
+    /// perpendicular distance of the nearest wall. When `depth` is given it
+    /// also receives the distance of every wall pixel in the column.
+    fn render_column(&self, i: usize, pixels: &mut [u32], mut depth: Option<&mut [f32]>) -> f32 {
+        let player = self.player;
+        let layers = self.layers;
+        let builtin = self.builtin;
+        let fog = self.fog;
+        let block_size = self.block_size;
+        let block = block_size as f32;
+        let hh = self.height as f32 / 2.0;
+        let horizon = self.horizon;
+        let horizon_row = horizon.clamp(0.0, self.height as f32) as usize;
+
+        // Floor and ceiling rows use the same projection as the wall stakes, with
+        // the eye halfway up a block, so the planes meet the walls exactly.
+        let eye_height = block / 2.0;
+        let projection = self.height as f32 * 70.0 / block;
+
+        let current_ray = i as f32 / self.width as f32;
+        let angle = player.angle - (player.fov / 2.0) + (player.fov * current_ray);
+        let dir = Vec2::new(angle.cos(), angle.sin());
+        let cos_offset = (angle - player.angle).cos();
+
+        let sky_u = angle.rem_euclid(2.0 * PI) / (2.0 * PI);
+
+        for (j, pixel) in pixels.iter_mut().enumerate().take(horizon_row) {
+            let rows_from_horizon = horizon - (j as f32 + 0.5);
+            let distance = eye_height * projection / rows_from_horizon / cos_offset;
+            let world = player.pos + dir * distance;
+            let cell = layer_cell(
+                &layers.ceiling,
+                world.x as usize / block_size,
+                world.y as usize / block_size,
+            );
+            let u = (world.x / block).fract();
+            let v = (world.y / block).fract();
+
+            *pixel = builtin
+                .ceiling_color(cell, u, v)
+                .map(|color| self.beyond_view(color, distance))
+                .unwrap_or_else(|| builtin.sky.sample(sky_u, (j as f32 - (horizon - hh)) / hh));
+        }
+
+        for (j, pixel) in pixels.iter_mut().enumerate().skip(horizon_row) {
+            let rows_from_horizon = (j as f32 + 0.5) - horizon;
+            let distance = eye_height * projection / rows_from_horizon / cos_offset;
+            let world = player.pos + dir * distance;
+            let cell = layer_cell(
+                &layers.floor,
+                world.x as usize / block_size,
+                world.y as usize / block_size,
+            );
+            let u = (world.x / block).fract();
+            let v = (world.y / block).fract();
+
+            *pixel = self.beyond_view(builtin.floor_color(cell, u, v), distance);
+        }
+
+        // Walk past walls shorter than the tallest one in the map, drawing
+        // front to back; anything farther only shows above nearer tops.
+        let max_height = self.max_height;
+        let mut nearest = f32::INFINITY;
+        let mut clip_bottom = self.height as f32;
+        let ray = Ray::new(self.maze, self.doors, player.pos, angle, block_size)
+            .with_max_distance(self.view_distance);
+        for intersect in ray {
+            let distance = intersect.distance * cos_offset;
+            let height = layers.wall_height(intersect.cell.0, intersect.cell.1);
+            let brightness = match intersect.face {
+                Face::East | Face::West => SIDE_SHADE,
+                Face::North | Face::South => 1.0,
+            };
+            nearest = nearest.min(distance);
+
+            let stake_unit = (self.height as f32 / distance) * 70.0;
+            let stake_bottom = horizon + (stake_unit / 2.0);
+            let stake_top = stake_bottom - stake_unit * height;
+
+            let top = stake_top.max(0.0) as usize;
+            let bottom = (stake_bottom.min(clip_bottom).max(0.0) as usize).min(self.height);
+
+            for y in top..bottom {
+                let levels = (stake_bottom - (y as f32 + 0.5)) / stake_unit;
+                let v = 1.0 - levels.fract();
+                let color = match self.wall_textures.get(&intersect.impact) {
+                    Some(texture) => texture.sample(intersect.u, v),
+                    None => builtin.wall_color(intersect.impact, intersect.u, v),
+                };
+                pixels[y] = fog.apply(shade(color, brightness), intersect.distance);
+                if let Some(depth) = depth.as_deref_mut() {
+                    depth[y] = distance;
+                }
+            }
+
+            clip_bottom = clip_bottom.min(stake_top);
+            if height >= max_height || clip_bottom <= 0.0 {
+                break;
+            }
+        }
+
+        nearest
+    }
+
+    /// Fogs a floor or ceiling color, hiding it entirely past the view
+    /// distance where rays stop looking for walls.
+    fn beyond_view(&self, color: u32, distance: f32) -> u32 {
+        if distance > self.view_distance {
+            return self.fog.color;
+        }
+        self.fog.apply(color, distance)
+    }
+}
+
+/// Owns the scratch and depth buffers of the 3D view so they are allocated
+/// once and reused every frame.
+pub struct Renderer {
+    pub threads: usize,
+    pub fog: Fog,
+    /// How far rays look for walls, in world units. Past it the view shows
+    /// the sky and fog, which lets open maps fade out instead of ending at
+    /// the edge of the grid.
+    pub view_distance: f32,
+    width: usize,
+    height: usize,
+    horizon: f32,
+    pixels: Vec<u32>,
+    column_depth: Vec<f32>,
+    pixel_depth: Option<Vec<f32>>,
+    builtin: Arc<Builtin>,
+    wall_textures: HashMap<char, Texture>,
+}
+
+impl Renderer {
+    pub fn new(width: usize, height: usize, threads: usize, fog: Fog) -> image::ImageResult<Self> {
+        let builtin = BUILTIN
+            .get_or_try_init(|| Builtin::load().map(Arc::new))?
+            .clone();
+        Ok(Renderer {
+            threads,
+            fog,
+            view_distance: f32::INFINITY,
+            width,
+            height,
+            horizon: height as f32 / 2.0,
+            pixels: vec![0; width * height],
+            column_depth: vec![f32::INFINITY; width],
+            pixel_depth: None,
+            builtin,
+            wall_textures: HashMap::new(),
+        })
+    }
+
+    /// Replaces the built-in texture of each tile in `textures` with the
+    /// image at its path, as assigned by a map file.
+    pub fn set_wall_textures(
+        &mut self,
+        textures: &BTreeMap<char, String>,
+    ) -> image::ImageResult<()> {
+        self.wall_textures.clear();
+        for (&tile, path) in textures {
+            self.wall_textures.insert(tile, Texture::load(path)?);
+        }
+        Ok(())
+    }
+
+    /// Keeps a depth value for every wall pixel, not just the nearest wall
+    /// per column. Needed for sprites to show above walls shorter than them.
+    pub fn set_pixel_depth(&mut self, enabled: bool) {
+        self.pixel_depth = enabled.then(|| vec![f32::INFINITY; self.width * self.height]);
+    }
+
+    /// Renders the walls, floor and ceiling. Columns are split into one stripe
+    /// per thread, each written to its own column-major area of the scratch
+    /// buffer and copied back afterwards, so any thread count produces
+    /// exactly the same frame.
+    pub fn render3d(
+        &mut self,
+        framebuffer: &mut Framebuffer,
+        player: &Player,
+        maze: &Maze,
+        doors: &Doors,
+        layers: &Layers,
+        block_size: usize,
+    ) {
+        let width = self.width;
+        let height = self.height;
+        let threads = self.threads.max(1);
+        // Looking up or down shears the view instead of tilting it: the
+        // horizon moves by as many rows as a wall that far above or below
+        // eye level would, and every column is drawn around it.
+        let projection = height as f32 * 70.0 / block_size as f32;
+        self.horizon = height as f32 / 2.0 + (player.pitch.tan() * projection).round();
+        let scene = Scene {
+            player,
+            maze,
+            doors,
+            layers,
+            max_height: layers.max_wall_height(),
+            fog: &self.fog,
+            view_distance: self.view_distance,
+            horizon: self.horizon,
+            builtin: &self.builtin,
+            wall_textures: &self.wall_textures,
+            block_size,
+            width,
+            height,
+        };
+
+        let stripe = width.div_ceil(threads);
+        let mut pixel_depth = self.pixel_depth.as_mut().map(|depth| {
+            depth.fill(f32::INFINITY);
+            depth.chunks_mut(stripe * height)
+        });
+
+        std::thread::scope(|scope| {
+            let stripes = self
+                .pixels
+                .chunks_mut(stripe * height)
+                .zip(self.column_depth.chunks_mut(stripe))
+                .enumerate();
+            for (n, (pixels, column_depth)) in stripes {
+                let scene = &scene;
+                let mut depth = pixel_depth.as_mut().and_then(|chunks| chunks.next());
+                let mut work = move || {
+                    for (k, (pixels, nearest)) in pixels
+                        .chunks_mut(height)
+                        .zip(column_depth.iter_mut())
+                        .enumerate()
+                    {
+                        let depth = depth
+                            .as_deref_mut()
+                            .map(|depth| &mut depth[k * height..(k + 1) * height]);
+                        *nearest = scene.render_column(n * stripe + k, pixels, depth);
+                    }
+                };
+                if threads > 1 {
+                    scope.spawn(work);
+                } else {
+                    work();
+                }
+            }
+        });
+
+        for i in 0..width {
+            for j in 0..height {
+                framebuffer.buffer[j * framebuffer.width + i] = self.pixels[i * height + j];
+            }
+        }
+    }
+
+    /// Draws a billboard sprite, hidden behind walls by the per-pixel depth
+    /// when enabled and by the nearest wall of each column otherwise.
+    pub fn render_sprite(
+        &self,
+        framebuffer: &mut Framebuffer,
+        player: &Player,
+        pos: &Vec2,
+        texture: &Texture,
+    ) {
+        let sprite_angle = (pos.y - player.pos.y).atan2(pos.x - player.pos.x);
+        let sprite_distance =
+            ((player.pos.x - pos.x).powi(2) + (player.pos.y - pos.y).powi(2)).sqrt();
+
+        let screen_height = framebuffer.height as f32;
+        let screen_width = framebuffer.width as f32;
+
+        let sprite_size = (screen_height / sprite_distance) * 70.0;
+
+        // Between -PI and PI, so a sprite behind the player isn't projected
+        // as if it were in front.
+        let relative_angle = (sprite_angle - player.angle + PI).rem_euclid(2.0 * PI) - PI;
+        // Distance along the view direction, measured like the wall depths.
+        let sprite_depth = sprite_distance * relative_angle.cos();
+        if sprite_depth <= 0.0 {
+            return;
+        }
+        let start_x = ((relative_angle).tan() * (screen_width / 2.0) / (player.fov / 2.0))
+            + (screen_width / 2.0)
+            - (sprite_size / 2.0);
+        if start_x >= screen_width || start_x + sprite_size <= 0.0 {
+            return;
+        }
+        let start_y = self.horizon - (sprite_size / 2.0);
+
+        let end_x = ((start_x + sprite_size) as usize).min(self.width);
+        let end_y = ((start_y + sprite_size) as usize).min(self.height);
+        // Rows cut off at the top still count toward the texture row.
+        let top = start_y;
+
+        let start_x = start_x.max(0.0) as usize;
+        let start_y = start_y.max(0.0) as usize;
+
+        let ignore_color = 0xFF66C4;
+        let threshold = 150;
+
+        for x in start_x..end_x {
+            if self.pixel_depth.is_none() && sprite_depth >= self.column_depth[x] {
+                continue;
+            }
+
+            for y in start_y..end_y {
+                if let Some(depth) = &self.pixel_depth {
+                    if sprite_depth >= depth[x * self.height + y] {
+                        continue;
+                    }
+                }
+
+                let tx = (((x - start_x) * texture.width as usize / sprite_size as usize) as u32)
+                    .min(texture.width - 1);
+                let ty = (((y as f32 - top) * texture.height as f32 / sprite_size) as u32)
+                    .min(texture.height - 1);
+
+                let color = texture.get_pixel_color(tx, ty);
+                let color_diff = color_distance(color, ignore_color);
+
+                if color_diff > threshold {
+                    framebuffer.set_current_color(self.fog.apply(color, sprite_distance));
+                    framebuffer.point(x, y);
+                }
+            }
+        }
+    }
+
+    pub fn render_enemies(&self, framebuffer: &mut Framebuffer, player: &Player, enemies: &[Vec2]) {
+        for enemy in enemies {
+            self.render_sprite(framebuffer, player, enemy, &self.builtin.enemy);
+        }
+    }
+}
+
+fn cell_to_color(cell: char) -> u32 {
+    match cell {
+        '+' | '-' | '|' => 0x333333,
+        ' ' => 0xAAAAAA,
+        'g' => 0xFF0000,
+        'D' => 0x8B5A2B,
+        _ => 0x333333,
+    }
+}
+
+fn draw_cell(framebuffer: &mut Framebuffer, xo: usize, yo: usize, block_size: usize, cell: char) {
+    for x in xo..xo + block_size {
+        for y in yo..yo + block_size {
+            if cell != ' ' {
+                let color = cell_to_color(cell);
+                framebuffer.set_current_color(color);
+                framebuffer.point(x, y);
+            }
+        }
+    }
+}
+
+/// Draws the top-down debug view, scaled down so the whole maze fits the
+/// framebuffer and at most one pixel per world unit.
+pub fn render2d(
+    framebuffer: &mut Framebuffer,
+    player: &Player,
+    maze: &Maze,
+    doors: &Doors,
+    block_size: usize,
+) {
+    let cell_size = (framebuffer.width / maze.width().max(1))
+        .min(framebuffer.height / maze.height().max(1))
+        .clamp(1, block_size);
+    let scale = cell_size as f32 / block_size as f32;
+    let point = |framebuffer: &mut Framebuffer, pos: Vec2| {
+        framebuffer.point((pos.x * scale) as usize, (pos.y * scale) as usize);
+    };
+
+    for (row, cells) in maze.rows().iter().enumerate() {
+        for (col, &cell) in cells.iter().enumerate() {
+            draw_cell(
+                framebuffer,
+                col * cell_size,
+                row * cell_size,
+                cell_size,
+                cell,
+            );
+        }
+    }
+    framebuffer.set_current_color(0x00FF00);
+    point(framebuffer, player.pos);
+
+    let num_rays = 100;
+    for i in 0..num_rays {
+        let current_ray = i as f32 / num_rays as f32;
+        let angle = player.angle - (player.fov / 2.0) + (player.fov * current_ray);
+        let intersect = cast_ray(framebuffer, maze, doors, player, angle, block_size, false);
+
+        framebuffer.set_current_color(0xFFFFFF);
+        let dir = Vec2::new(angle.cos(), angle.sin());
+        let mut d = 0.0;
+        while d < intersect.distance {
+            point(framebuffer, player.pos + dir * d);
+            d += 1.0 / scale;
+        }
+        // A ray that ran out of map or view distance has no cell or face to
+        // mark.
+        if !intersect.is_hit() {
+            continue;
+        }
+
+        let (col, row) = intersect.cell;
+        if intersect.impact != 'g' {
+            framebuffer.set_current_color(0x555555);
+            for x in col * cell_size..(col + 1) * cell_size {
+                for y in row * cell_size..(row + 1) * cell_size {
+                    framebuffer.point(x, y);
+                }
+            }
+        }
+
+        let color = match intersect.face {
+            Face::North | Face::South => 0xFF0000,
+            Face::East | Face::West => 0x0000FF,
+        };
+        framebuffer.set_current_color(color);
+        let normal = intersect.face.normal();
+        for d in 0..8 {
+            point(framebuffer, intersect.hit + normal * (d as f32 / scale));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Splitting the columns across threads must not change a single pixel.
+    #[test]
+    fn threaded_render_matches_single_thread() {
+        let block_size = 100;
+        let maze = Maze::load("maze.txt").unwrap();
+        let layers = Layers::load("maze.txt");
+        let doors = Doors::from_maze(&maze);
+        let player = Player {
+            pos: Vec2::new(150.0, 150.0),
+            angle: 0.3,
+            fov: PI / 3.0,
+            pitch: 0.0,
+        };
+
+        let render = |threads| {
+            let mut framebuffer = Framebuffer::new(320, 240);
+            let mut renderer = Renderer::new(320, 240, threads, Fog::default()).unwrap();
+            renderer.render3d(
+                &mut framebuffer,
+                &player,
+                &maze,
+                &doors,
+                &layers,
+                block_size,
+            );
+            framebuffer.buffer
+        };
+        let single = render(1);
+        for threads in [3, 7, 16] {
+            assert!(render(threads) == single, "{} threads differ", threads);
+        }
+    }
+}
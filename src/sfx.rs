@@ -2,7 +2,7 @@ use lazy_static::lazy_static;
 use rodio::Sink;
 use rodio::Source;
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::BufReader;
 use std::sync::Mutex;
 
 lazy_static! {
@@ -12,7 +12,7 @@ lazy_static! {
 pub fn play_footstep_sound(
     stream_handle: &rodio::OutputStreamHandle,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let file = File::open("assets\\walking.mp3")?;
+    let file = File::open("assets/walking.mp3")?;
     let buf_reader = BufReader::new(file);
     let source = rodio::Decoder::new(buf_reader)?.convert_samples::<f32>();
     let mut sink_guard = FOOTSTEP_SINK.lock().unwrap();
@@ -24,3 +24,13 @@ pub fn play_footstep_sound(
     *sink_guard = Some(sink);
     Ok(())
 }
+
+pub fn play_door_sound(
+    stream_handle: &rodio::OutputStreamHandle,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file = File::open("assets/door.wav")?;
+    let buf_reader = BufReader::new(file);
+    let source = rodio::Decoder::new(buf_reader)?.convert_samples::<f32>();
+    stream_handle.play_raw(source)?;
+    Ok(())
+}
@@ -12,7 +12,11 @@ pub struct Texture {
 
 impl Texture {
     pub fn new(file_path: &str) -> Texture {
-        let img = ImageReader::open(file_path).unwrap().decode().unwrap();
+        Texture::load(file_path).unwrap()
+    }
+
+    pub fn load(file_path: &str) -> image::ImageResult<Texture> {
+        let img = ImageReader::open(file_path)?.decode()?;
         let width = img.width();
         let height = img.height();
         let mut t = Texture {
@@ -22,7 +26,7 @@ impl Texture {
             color_array: vec![vec![0; height as usize]; width as usize],
         };
         t.load_array();
-        t
+        Ok(t)
     }
 
     fn load_array(&mut self) {
@@ -44,4 +48,10 @@ impl Texture {
         }
         self.color_array[x as usize][y as usize]
     }
+
+    pub fn sample(&self, u: f32, v: f32) -> u32 {
+        let x = ((u * self.width as f32) as u32).min(self.width - 1);
+        let y = ((v * self.height as f32) as u32).min(self.height - 1);
+        self.get_pixel_color(x, y)
+    }
 }
@@ -0,0 +1,37 @@
+use std::time::Instant;
+
+/// The longest stretch of real time one frame may simulate. Anything more,
+/// such as a window drag, is dropped rather than played back all at once.
+const MAX_FRAME_TIME: f32 = 0.25;
+
+/// Splits real time into fixed simulation ticks, so the game advances the
+/// same way whether frames take 2 ms or 50 ms to draw.
+pub struct FixedTimestep {
+    /// Seconds of game time per tick.
+    pub step: f32,
+    accumulator: f32,
+    last: Instant,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32) -> Self {
+        FixedTimestep {
+            step,
+            accumulator: 0.0,
+            last: Instant::now(),
+        }
+    }
+
+    /// Measures the time since the last call and returns how many ticks it
+    /// covers. The remainder carries over to the next frame.
+    pub fn ticks(&mut self) -> u32 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f32();
+        self.last = now;
+
+        self.accumulator += elapsed.min(MAX_FRAME_TIME);
+        let ticks = (self.accumulator / self.step) as u32;
+        self.accumulator -= ticks as f32 * self.step;
+        ticks
+    }
+}